@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Builds about 64 KiB of MUTF-8 data by repeating `text`.
+fn input(text: &str) -> Vec<u8> {
+    let mut s = String::new();
+    while s.len() < 64 * 1024 {
+        s.push_str(text);
+    }
+    mutf8::encode(&s).into_owned()
+}
+
+fn inputs() -> [(&'static str, Vec<u8>); 4] {
+    [
+        // Class and member names, as found in the constant pool of a class file.
+        (
+            "ascii",
+            input("java/lang/Object<init>()VgetClassLjava/lang/String;"),
+        ),
+        // Every other character is written as the null pair `0xC0 0x80`.
+        ("nul_heavy", input("a\0")),
+        // 3-byte sequences only.
+        (
+            "bmp",
+            input("\u{6F22}\u{5B57}\u{304B}\u{306A}\u{30AB}\u{30CA}\u{D55C}\u{AE00}"),
+        ),
+        // Every supplementary character is written as a surrogate pair.
+        ("supplementary", input("\u{1F600}a\u{10401}\u{1D11E}")),
+    ]
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, bytes) in inputs() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &bytes, |b, bytes| {
+            b.iter(|| mutf8::decode(black_box(bytes)));
+        });
+    }
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    for (name, bytes) in inputs() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &bytes, |b, bytes| {
+            b.iter(|| mutf8::validate(black_box(bytes)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, validate);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+use crate::{decode_mutf8_into, encode_mutf8_into, is_valid, len, Encoder, EncoderResult, Error};
+use alloc::{str::from_utf8, string::String, vec::Vec};
+use core::fmt;
+
+/// Encodes a string slice to MUTF-8, writing into a caller-provided buffer.
+///
+/// This is the same as [`encode`](crate::encode), except that the bytes are
+/// written to the start of `out` instead of into a newly allocated vector. On
+/// success, the number of bytes written is returned.
+///
+/// # Errors
+///
+/// Returns [`BufferTooSmall`] if the MUTF-8 representation of the string does
+/// not fit in `out`, in which case the contents of `out` are unspecified.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mut buffer = [0; 8];
+/// let written = mutf8::encode_into("a\0b", &mut buffer)?;
+/// assert_eq!(&buffer[..written], &[b'a', 0xC0, 0x80, b'b']);
+///
+/// let error = mutf8::encode_into("\u{10401}", &mut buffer[..4]).unwrap_err();
+/// assert_eq!(error.encoded_len(), 6);
+/// # Ok::<(), mutf8::BufferTooSmall>(())
+/// ```
+#[inline]
+pub fn encode_into(s: &str, out: &mut [u8]) -> Result<usize, BufferTooSmall> {
+    if s.len() <= out.len() && is_valid(s) {
+        out[..s.len()].copy_from_slice(s.as_bytes());
+        return Ok(s.len());
+    }
+
+    match Encoder::new().encode_from_utf8(s, out, true) {
+        (EncoderResult::InputEmpty, _, written) => Ok(written),
+        (EncoderResult::OutputFull, ..) => Err(BufferTooSmall::new(len(s))),
+    }
+}
+
+/// Encodes a string slice to MUTF-8, appending it to a vector.
+///
+/// This is the same as [`encode`](crate::encode), except that the bytes are
+/// appended to `out`, so one buffer can be reused for any number of strings.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mut buffer = Vec::new();
+/// for str in ["a", "\0", "\u{10401}"] {
+///     mutf8::encode_append(str, &mut buffer);
+/// }
+/// assert_eq!(buffer, &[b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]);
+/// ```
+#[inline]
+pub fn encode_append(s: &str, out: &mut Vec<u8>) {
+    if is_valid(s) {
+        out.extend_from_slice(s.as_bytes());
+    } else {
+        encode_append_mutf8(s, out);
+    }
+}
+
+#[inline(never)]
+#[cold]
+pub(crate) fn encode_append_mutf8(s: &str, out: &mut Vec<u8>) {
+    out.reserve(len(s));
+    encode_mutf8_into(s, out);
+}
+
+/// Decodes MUTF-8 bytes, appending the result to a string.
+///
+/// This is the same as [`decode`](crate::decode), except that the decoded
+/// string is appended to `out`, so one buffer can be reused for any number of
+/// strings.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the input is invalid MUTF-8 data, in which case `out`
+/// is left untouched.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mut buffer = String::new();
+/// mutf8::decode_append(b"a", &mut buffer)?;
+/// mutf8::decode_append(&[0xC0, 0x80], &mut buffer)?;
+/// mutf8::decode_append(&[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81], &mut buffer)?;
+/// assert_eq!(buffer, "a\0\u{10401}");
+///
+/// let error = mutf8::decode_append(&[b'b', 0xED, 0xA0, 0x81], &mut buffer).unwrap_err();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(buffer, "a\0\u{10401}");
+/// # Ok::<(), mutf8::Error>(())
+/// ```
+#[inline]
+pub fn decode_append(bytes: &[u8], out: &mut String) -> Result<(), Error> {
+    match from_utf8(bytes) {
+        Ok(s) => {
+            out.push_str(s);
+            Ok(())
+        }
+        Err(error) => decode_mutf8_into(bytes, error.valid_up_to(), out, false),
+    }
+}
+
+/// An error thrown by [`encode_into`] when the buffer is too small to hold the
+/// encoded string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferTooSmall {
+    len: usize,
+}
+
+impl BufferTooSmall {
+    #[inline]
+    pub(crate) const fn new(len: usize) -> BufferTooSmall {
+        BufferTooSmall { len }
+    }
+
+    /// Returns the length in bytes of the MUTF-8 representation of the string,
+    /// which is the smallest buffer it fits in.
+    #[must_use]
+    #[inline]
+    pub const fn encoded_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Display for BufferTooSmall {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer too small: encoded string takes {} bytes",
+            self.len
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for BufferTooSmall {}
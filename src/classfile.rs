@@ -0,0 +1,475 @@
+//! Reading and writing the constant pool of a JVM class file.
+//!
+//! Every name, descriptor and string literal of a class file is stored in its
+//! constant pool as a `CONSTANT_Utf8_info` entry holding MUTF-8 data. A
+//! [`ConstantPool`] keeps those entries as raw bytes and only decodes one when
+//! asked to, so a class file can be scanned without decoding any string that
+//! is never looked at. Every other entry is kept as-is.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use mutf8::classfile::ConstantPool;
+//!
+//! let class = &[
+//!     0xCA, 0xFE, 0xBA, 0xBE, // magic
+//!     0x00, 0x00, 0x00, 0x34, // version 52.0
+//!     0x00, 0x03, // constant_pool_count
+//!     0x07, 0x00, 0x02, // #1 = Class #2
+//!     0x01, 0x00, 0x04, b'F', b'o', b'o', b'!', // #2 = Utf8 "Foo!"
+//!     0x00, 0x21, // access_flags, and so on
+//! ];
+//!
+//! let (mut pool, end) = ConstantPool::from_class(class).unwrap();
+//! assert_eq!(pool.utf8(2), Some(Ok("Foo!".into())));
+//! assert_eq!(&class[end..], &[0x00, 0x21]);
+//!
+//! // Replace the string, then put the class file back together.
+//! assert!(pool.set_utf8(2, "Bar\0"));
+//! let mut modified = class[..8].to_vec();
+//! pool.write(&mut modified).unwrap();
+//! modified.extend_from_slice(&class[end..]);
+//!
+//! let (pool, _) = ConstantPool::from_class(&modified).unwrap();
+//! assert_eq!(pool.utf8(2), Some(Ok("Bar\0".into())));
+//! ```
+
+use crate::{data::MAX_UTF_LEN, decode, encode, Error, TooLong};
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+/// The magic number every class file starts with.
+const MAGIC: u32 = 0xCAFE_BABE;
+
+/// The offset of the constant pool in a class file, which follows the magic
+/// number and the minor and major versions.
+const POOL_OFFSET: usize = 8;
+
+/// The tag of a `CONSTANT_Utf8_info` entry.
+const UTF8: u8 = 1;
+
+/// The constant pool of a JVM class file.
+///
+/// Entries are looked up by their index in the pool, which starts at 1. An
+/// index that does not name an entry, including the unusable index following a
+/// `CONSTANT_Long_info` or `CONSTANT_Double_info` entry, has no entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConstantPool<'a> {
+    entries: Vec<Constant<'a>>,
+}
+
+/// An entry of a [`ConstantPool`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Constant<'a> {
+    /// A `CONSTANT_Utf8_info` entry, holding the raw MUTF-8 bytes of its
+    /// string.
+    Utf8(Cow<'a, [u8]>),
+    /// Any other entry, holding its tag and the bytes that follow it.
+    Other {
+        /// The tag of the entry.
+        tag: u8,
+        /// The bytes that follow the tag.
+        info: &'a [u8],
+    },
+    /// The unusable index following a `CONSTANT_Long_info` or
+    /// `CONSTANT_Double_info` entry, which takes up two indices.
+    Unusable,
+}
+
+impl<'a> ConstantPool<'a> {
+    /// Parses the constant pool of a class file.
+    ///
+    /// On success, the constant pool is returned along with the offset in
+    /// `class` at which it ends, where the access flags of the class begin.
+    /// The constant pool itself always begins at offset 8.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClassFileError`] if `class` does not start with the magic
+    /// number of a class file, or if the constant pool is malformed.
+    #[inline]
+    pub fn from_class(class: &'a [u8]) -> Result<(ConstantPool<'a>, usize), ClassFileError> {
+        let Some(header) = class.get(..POOL_OFFSET) else {
+            return Err(ClassFileError::UnexpectedEnd);
+        };
+        let magic = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        if magic != MAGIC {
+            return Err(ClassFileError::InvalidMagic(magic));
+        }
+        let (pool, read) = ConstantPool::read(&class[POOL_OFFSET..])?;
+        Ok((pool, POOL_OFFSET + read))
+    }
+
+    /// Parses a constant pool, starting with its `constant_pool_count`.
+    ///
+    /// On success, the constant pool is returned along with the number of
+    /// bytes it takes up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ClassFileError`] if the constant pool is malformed.
+    pub fn read(bytes: &'a [u8]) -> Result<(ConstantPool<'a>, usize), ClassFileError> {
+        let count = match bytes {
+            &[high, low, ..] => u16::from_be_bytes([high, low]),
+            _ => return Err(ClassFileError::UnexpectedEnd),
+        };
+        let mut entries = Vec::with_capacity(usize::from(count.saturating_sub(1)));
+        let mut offset = 2;
+
+        let mut index = 1;
+        while index < count {
+            let Some(&tag) = bytes.get(offset) else {
+                return Err(ClassFileError::UnexpectedEnd);
+            };
+            offset += 1;
+
+            let info_len = match tag {
+                UTF8 => match bytes.get(offset..offset + 2) {
+                    Some(&[high, low]) => 2 + usize::from(u16::from_be_bytes([high, low])),
+                    _ => return Err(ClassFileError::UnexpectedEnd),
+                },
+                // Class, String, MethodType, Module and Package.
+                7 | 8 | 16 | 19 | 20 => 2,
+                // MethodHandle.
+                15 => 3,
+                // Integer, Float, Fieldref, Methodref, InterfaceMethodref,
+                // NameAndType, Dynamic and InvokeDynamic.
+                3 | 4 | 9..=12 | 17 | 18 => 4,
+                // Long and Double.
+                5 | 6 if index + 1 < count => 8,
+                _ => return Err(ClassFileError::InvalidConstant { index, tag }),
+            };
+            let Some(info) = bytes.get(offset..offset + info_len) else {
+                return Err(ClassFileError::UnexpectedEnd);
+            };
+            offset += info_len;
+
+            if tag == UTF8 {
+                entries.push(Constant::Utf8(Cow::Borrowed(&info[2..])));
+            } else {
+                entries.push(Constant::Other { tag, info });
+            }
+            if tag == 5 || tag == 6 {
+                entries.push(Constant::Unusable);
+                index += 1;
+            }
+            index += 1;
+        }
+
+        Ok((ConstantPool { entries }, offset))
+    }
+
+    /// Returns the `constant_pool_count` of the constant pool, which is one
+    /// more than the highest index in it.
+    ///
+    /// As indices start at 1, the count of an empty constant pool is 1. This
+    /// is also the case for a constant pool read from a count of 0, which the
+    /// JVM rejects.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constant pool holds more than 65534 indices, which one
+    /// read by [`read`](ConstantPool::read) never does, as its count is
+    /// itself a `u16`.
+    #[must_use]
+    #[inline]
+    pub fn count(&self) -> u16 {
+        let Ok(count) = u16::try_from(self.entries.len() + 1) else {
+            panic!("constant pool has more than 65534 indices");
+        };
+        count
+    }
+
+    /// Returns the entry at an index, or `None` if the index does not name an
+    /// entry.
+    #[must_use]
+    #[inline]
+    pub fn get(&self, index: u16) -> Option<&Constant<'a>> {
+        match self.entries.get(usize::from(index).checked_sub(1)?)? {
+            Constant::Unusable => None,
+            constant => Some(constant),
+        }
+    }
+
+    /// Decodes the string of the `CONSTANT_Utf8_info` entry at an index with
+    /// [`decode`], or returns `None` if the index does not name such an entry.
+    #[must_use]
+    #[inline]
+    pub fn utf8(&self, index: u16) -> Option<Result<Cow<'_, str>, Error>> {
+        match self.get(index)? {
+            Constant::Utf8(bytes) => Some(decode(bytes)),
+            _ => None,
+        }
+    }
+
+    /// Replaces the string of the `CONSTANT_Utf8_info` entry at an index with
+    /// one encoded by [`encode`].
+    ///
+    /// Returns `false`, leaving the constant pool untouched, if the index does
+    /// not name such an entry.
+    #[inline]
+    pub fn set_utf8(&mut self, index: u16, s: &str) -> bool {
+        let entry = usize::from(index)
+            .checked_sub(1)
+            .and_then(|index| self.entries.get_mut(index));
+        match entry {
+            Some(Constant::Utf8(bytes)) => {
+                *bytes = Cow::Owned(encode(s).into_owned());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns an iterator over the entries of the constant pool along with
+    /// their indices.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &Constant<'a>)> {
+        self.entries
+            .iter()
+            .zip(1..)
+            .filter(|(constant, _)| !matches!(constant, Constant::Unusable))
+            .map(|(constant, index)| (index, constant))
+    }
+
+    /// Writes the constant pool, starting with its `constant_pool_count`, the
+    /// way it is laid out in a class file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TooLong`] if the string of a `CONSTANT_Utf8_info` entry is
+    /// longer than 65535 bytes, in which case `out` is left untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count of the constant pool does not fit in a `u16`, as
+    /// described under [`count`](ConstantPool::count).
+    pub fn write(&self, out: &mut Vec<u8>) -> Result<(), TooLong> {
+        let mut len = 2;
+        for constant in &self.entries {
+            len += match constant {
+                Constant::Utf8(bytes) if bytes.len() > MAX_UTF_LEN => {
+                    return Err(TooLong::new(bytes.len()));
+                }
+                Constant::Utf8(bytes) => 3 + bytes.len(),
+                Constant::Other { info, .. } => 1 + info.len(),
+                Constant::Unusable => 0,
+            };
+        }
+
+        out.reserve(len);
+        out.extend_from_slice(&self.count().to_be_bytes());
+        for constant in &self.entries {
+            match constant {
+                Constant::Utf8(bytes) => {
+                    // The length was checked against `MAX_UTF_LEN` above.
+                    #[allow(clippy::cast_possible_truncation)]
+                    let len = bytes.len() as u16;
+                    out.push(UTF8);
+                    out.extend_from_slice(&len.to_be_bytes());
+                    out.extend_from_slice(bytes);
+                }
+                Constant::Other { tag, info } => {
+                    out.push(*tag);
+                    out.extend_from_slice(info);
+                }
+                Constant::Unusable => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error thrown when a class file or its constant pool is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClassFileError {
+    /// The class file does not start with `0xCAFEBABE`.
+    InvalidMagic(u32),
+    /// The input ended before the end of the constant pool.
+    UnexpectedEnd,
+    /// An entry has an unknown tag, or is a `CONSTANT_Long_info` or
+    /// `CONSTANT_Double_info` entry that does not fit in the constant pool.
+    InvalidConstant {
+        /// The index of the entry.
+        index: u16,
+        /// The tag of the entry.
+        tag: u8,
+    },
+}
+
+impl fmt::Display for ClassFileError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ClassFileError::InvalidMagic(magic) => {
+                write!(f, "invalid class file magic number {magic:#010X}")
+            }
+            ClassFileError::UnexpectedEnd => f.write_str("unexpected end of constant pool"),
+            ClassFileError::InvalidConstant { index, tag } => {
+                write!(f, "invalid constant pool entry #{index} with tag {tag}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for ClassFileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Compiled from `tests/classfile/Constants.java`.
+    const CLASS: &[u8] = include_bytes!("../tests/classfile/Constants.class");
+
+    /// The offset at which the constant pool of `CLASS` ends.
+    const POOL_END: usize = 0x121;
+
+    /// The offset of entry #26 of `CLASS`, the Utf8 entry `STRING`.
+    const STRING_ENTRY: usize = 0xBF;
+
+    #[test]
+    fn long_and_double_take_two_indices() {
+        let (pool, end) = ConstantPool::from_class(CLASS).unwrap();
+        assert_eq!(end, POOL_END);
+        assert_eq!(pool.count(), 34);
+
+        // #9 is a Long, so #10 is unusable and #11 is the String after it.
+        let long = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+        assert_eq!(
+            pool.get(9),
+            Some(&Constant::Other {
+                tag: 5,
+                info: &long
+            })
+        );
+        assert_eq!(pool.get(10), None);
+        assert_eq!(pool.utf8(10), None);
+        assert_eq!(
+            pool.get(11),
+            Some(&Constant::Other {
+                tag: 8,
+                info: &[0x00, 12]
+            })
+        );
+        assert_eq!(pool.utf8(12), Some(Ok("\0\u{1F600}".into())));
+
+        // #24 is a Double, so #25 is unusable and #26 is the Utf8 after it.
+        let double = 1.5_f64.to_bits().to_be_bytes();
+        assert_eq!(
+            pool.get(24),
+            Some(&Constant::Other {
+                tag: 6,
+                info: &double
+            })
+        );
+        assert_eq!(pool.get(25), None);
+        assert_eq!(pool.utf8(25), None);
+        assert_eq!(pool.utf8(23), Some(Ok("D".into())));
+        assert_eq!(pool.utf8(26), Some(Ok("STRING".into())));
+        assert_eq!(pool.utf8(33), Some(Ok("Constants.java".into())));
+
+        assert_eq!(pool.get(0), None);
+        assert_eq!(pool.get(34), None);
+        let indices: Vec<_> = pool.iter().map(|(index, _)| index).collect();
+        let expected: Vec<_> = (1..34)
+            .filter(|&index| index != 10 && index != 25)
+            .collect();
+        assert_eq!(indices, expected);
+    }
+
+    #[test]
+    fn set_utf8_after_long_and_double() {
+        let (mut pool, end) = ConstantPool::from_class(CLASS).unwrap();
+        assert!(!pool.set_utf8(9, "x"));
+        assert!(!pool.set_utf8(10, "x"));
+        assert!(!pool.set_utf8(25, "x"));
+        assert!(!pool.set_utf8(34, "x"));
+        assert!(pool.set_utf8(26, "RENAMED"));
+        assert_eq!(pool.utf8(26), Some(Ok("RENAMED".into())));
+
+        let mut written = CLASS[..POOL_OFFSET].to_vec();
+        pool.write(&mut written).unwrap();
+        written.extend_from_slice(&CLASS[end..]);
+
+        // Only entry #26 changes.
+        let entry = [UTF8, 0x00, 0x06, b'S', b'T', b'R', b'I', b'N', b'G'];
+        assert_eq!(CLASS[STRING_ENTRY..STRING_ENTRY + entry.len()], entry);
+        let mut expected = CLASS[..STRING_ENTRY].to_vec();
+        expected.extend_from_slice(&[UTF8, 0x00, 0x07]);
+        expected.extend_from_slice(b"RENAMED");
+        expected.extend_from_slice(&CLASS[STRING_ENTRY + entry.len()..]);
+        assert_eq!(written, expected);
+
+        let (pool, end) = ConstantPool::from_class(&written).unwrap();
+        assert_eq!(end, POOL_END + 1);
+        assert_eq!(pool.utf8(26), Some(Ok("RENAMED".into())));
+        assert_eq!(pool.utf8(27), Some(Ok("Ljava/lang/String;".into())));
+    }
+
+    #[test]
+    fn round_trip() {
+        let (pool, end) = ConstantPool::from_class(CLASS).unwrap();
+        let mut written = CLASS[..POOL_OFFSET].to_vec();
+        pool.write(&mut written).unwrap();
+        assert_eq!(written.len(), end);
+        written.extend_from_slice(&CLASS[end..]);
+        assert_eq!(written, CLASS);
+    }
+
+    #[test]
+    fn long_without_room_for_second_index() {
+        // A Long as the last entry of a pool with a count of 2.
+        let bytes = [0x00, 0x02, 0x05, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert_eq!(
+            ConstantPool::read(&bytes),
+            Err(ClassFileError::InvalidConstant { index: 1, tag: 5 }),
+        );
+
+        let bytes = [0x00, 0x03, 0x06, 0, 0, 0, 0, 0, 0, 0, 1];
+        let (pool, read) = ConstantPool::read(&bytes).unwrap();
+        assert_eq!(read, bytes.len());
+        assert_eq!(
+            pool.entries,
+            vec![
+                Constant::Other {
+                    tag: 6,
+                    info: &bytes[3..]
+                },
+                Constant::Unusable
+            ]
+        );
+    }
+
+    #[test]
+    fn count_at_limit() {
+        assert_eq!(ConstantPool::default().count(), 1);
+        let (pool, read) = ConstantPool::read(&[0x00, 0x00]).unwrap();
+        assert_eq!((pool.count(), read), (1, 2));
+
+        // 65534 Integer entries, the most a 16-bit count allows.
+        let mut bytes = vec![0xFF, 0xFF];
+        for _ in 1..u16::MAX {
+            bytes.extend_from_slice(&[0x03, 0x00, 0x00, 0x00, 0x2A]);
+        }
+        let (pool, read) = ConstantPool::read(&bytes).unwrap();
+        assert_eq!((pool.count(), read), (u16::MAX, bytes.len()));
+        let mut written = Vec::new();
+        pool.write(&mut written).unwrap();
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    #[should_panic = "constant pool has more than 65534 indices"]
+    fn count_past_limit() {
+        let pool = ConstantPool {
+            entries: vec![Constant::Unusable; usize::from(u16::MAX)],
+        };
+        let _ = pool.count();
+    }
+}
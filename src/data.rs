@@ -0,0 +1,311 @@
+use crate::{
+    decode, encode, encode_append,
+    error::{Error, Invalid},
+    is_valid, len,
+};
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+/// The most bytes the MUTF-8 payload of a length-prefixed string can take up.
+pub(crate) const MAX_UTF_LEN: usize = u16::MAX as usize;
+
+/// Reads a string framed the way `java.io.DataInput.readUTF` expects it.
+///
+/// The string is made up of a big-endian `u16` holding the length of the
+/// payload in bytes, followed by the MUTF-8 payload itself, which is decoded by
+/// the same rules as [`decode`]. On success, the decoded string is returned
+/// along with the total number of bytes the frame takes up, prefix included.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the payload is invalid MUTF-8 data, or if the input
+/// ends before the end of the frame, in which case
+/// [`error_len`](Error::error_len) is `None`. The position given by
+/// [`valid_up_to`](Error::valid_up_to) is relative to the start of the frame.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+///
+/// let data = &[0x00, 0x05, b'a', 0xC0, 0x80, b'b', b'c', 0xFF];
+/// let (str, read) = mutf8::read_utf(data)?;
+/// assert_eq!(str, Cow::<str>::Owned("a\0bc".to_string()));
+/// assert_eq!(read, 7);
+///
+/// // The frame says 5 bytes follow, but there are only 2.
+/// let error = mutf8::read_utf(&data[..4]).unwrap_err();
+/// assert_eq!(error.error_len(), None);
+/// # Ok::<(), mutf8::Error>(())
+/// ```
+#[inline]
+pub fn read_utf(bytes: &[u8]) -> Result<(Cow<'_, str>, usize), Error> {
+    let Some((&[high, low], rest)) = bytes.split_first_chunk::<2>() else {
+        return Err(Error::new(0, Invalid::INCOMPLETE));
+    };
+    let len = usize::from(u16::from_be_bytes([high, low]));
+    let Some(payload) = rest.get(..len) else {
+        return Err(Error::new(2, Invalid::INCOMPLETE));
+    };
+    match decode(payload) {
+        Ok(s) => Ok((s, 2 + len)),
+        Err(error) => Err(error.offset(2)),
+    }
+}
+
+/// Writes a string framed the way `java.io.DataOutput.writeUTF` writes it.
+///
+/// The string is encoded by [`encode`] and appended to `out`, preceded by the
+/// length of its MUTF-8 representation as a big-endian `u16`.
+///
+/// # Errors
+///
+/// Returns [`TooLong`] if the MUTF-8 representation of the string is longer
+/// than 65535 bytes, in which case `out` is left untouched. This is the case in
+/// which Java throws a `UTFDataFormatException`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mut data = Vec::new();
+/// mutf8::write_utf("a\0b", &mut data)?;
+/// assert_eq!(data, vec![0x00, 0x04, b'a', 0xC0, 0x80, b'b']);
+///
+/// let str = "\0".repeat(40_000);
+/// let error = mutf8::write_utf(&str, &mut data).unwrap_err();
+/// assert_eq!(error.encoded_len(), 80_000);
+/// # Ok::<(), mutf8::TooLong>(())
+/// ```
+#[inline]
+pub fn write_utf(s: &str, out: &mut Vec<u8>) -> Result<(), TooLong> {
+    let encoded_len = len(s);
+    let Ok(prefix) = u16::try_from(encoded_len) else {
+        return Err(TooLong::new(encoded_len));
+    };
+    out.reserve(2 + encoded_len);
+    out.extend_from_slice(&prefix.to_be_bytes());
+    encode_append(s, out);
+    Ok(())
+}
+
+/// Encodes the longest prefix of a string slice that fits in `max_bytes` bytes
+/// of MUTF-8.
+///
+/// This is useful for fields with a length limit, such as a `CONSTANT_Utf8`
+/// entry of a class file, a string written by [`write_utf`] or a string in NBT
+/// data, all of which can take up at most 65535 bytes. Characters are never
+/// split, so neither is a surrogate pair nor `0xC0 0x80`.
+///
+/// The encoded prefix is returned along with the number of characters of `s`
+/// it holds. If the whole string fits, this function is no different than
+/// [`encode`]. The byte index of the end of the prefix in `s` is given by
+/// [`floor_char_boundary_for_len`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+///
+/// assert_eq!(mutf8::encode_truncated("Hello", 3), (Cow::Borrowed(&b"Hel"[..]), 3));
+///
+/// // The null character takes up 2 bytes, so it does not fit into the last
+/// // byte.
+/// assert_eq!(mutf8::encode_truncated("ab\0", 3), (Cow::Borrowed(&b"ab"[..]), 2));
+///
+/// // A supplementary character is never split between its surrogates.
+/// let (encoded, chars) = mutf8::encode_truncated("a\u{10401}", 6);
+/// assert_eq!((&*encoded, chars), (&b"a"[..], 1));
+/// ```
+#[must_use]
+#[inline]
+pub fn encode_truncated(s: &str, max_bytes: usize) -> (Cow<'_, [u8]>, usize) {
+    let (index, chars) = truncation_point(s, max_bytes);
+    (encode(&s[..index]), chars)
+}
+
+/// Returns the largest byte index in a string slice such that the prefix up to
+/// it fits in `max_bytes` bytes of MUTF-8.
+///
+/// The index is always at a character boundary of `s`, so `&s[..index]` is
+/// the longest prefix of `s` for which [`len`] returns at most `max_bytes`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let str = "a\0\u{10401}";
+/// assert_eq!(mutf8::floor_char_boundary_for_len(str, 2), 1);
+/// assert_eq!(mutf8::floor_char_boundary_for_len(str, 3), 2);
+/// assert_eq!(mutf8::floor_char_boundary_for_len(str, 8), 2);
+/// assert_eq!(mutf8::floor_char_boundary_for_len(str, 9), str.len());
+/// ```
+#[must_use]
+#[inline]
+pub fn floor_char_boundary_for_len(s: &str, max_bytes: usize) -> usize {
+    truncation_point(s, max_bytes).0
+}
+
+/// Returns the byte index of the end of the longest prefix of `s` that fits in
+/// `max_bytes` bytes of MUTF-8, along with how many characters it holds.
+fn truncation_point(s: &str, max_bytes: usize) -> (usize, usize) {
+    if s.len() <= max_bytes && is_valid(s) {
+        return (s.len(), s.chars().count());
+    }
+
+    let mut encoded_len = 0;
+    let mut chars = 0;
+    for (index, c) in s.char_indices() {
+        encoded_len += match c.len_utf8() {
+            _ if c == '\0' => 2,
+            4 => 6,
+            width => width,
+        };
+        if encoded_len > max_bytes {
+            return (index, chars);
+        }
+        chars += 1;
+    }
+    (s.len(), chars)
+}
+
+/// An error thrown by [`write_utf`] when a string is too long to be framed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooLong {
+    len: usize,
+}
+
+impl TooLong {
+    #[inline]
+    pub(crate) const fn new(len: usize) -> TooLong {
+        TooLong { len }
+    }
+
+    /// Returns the length in bytes of the MUTF-8 representation of the string
+    /// that was too long.
+    #[must_use]
+    #[inline]
+    pub const fn encoded_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Display for TooLong {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "encoded string too long: {} bytes, but at most {MAX_UTF_LEN} are allowed",
+            self.len,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for TooLong {}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl From<TooLong> for std::io::Error {
+    /// Converts a [`TooLong`] to an [`std::io::Error`] of kind
+    /// [`InvalidInput`](std::io::ErrorKind::InvalidInput).
+    #[inline]
+    fn from(error: TooLong) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    /// Checks that `s` is cut off after `expected` characters, which take up
+    /// `index` bytes of `s`.
+    fn assert_truncated(s: &str, max_bytes: usize, index: usize, expected: usize) {
+        let (encoded, chars) = encode_truncated(s, max_bytes);
+        assert_eq!(chars, expected);
+        assert_eq!(floor_char_boundary_for_len(s, max_bytes), index);
+        assert_eq!(encoded, encode(&s[..index]));
+        assert_eq!(encoded.len(), len(&s[..index]));
+        assert!(encoded.len() <= max_bytes);
+    }
+
+    /// Returns `ascii` bytes of ASCII followed by `tail`.
+    fn after_ascii(ascii: usize, tail: &str) -> String {
+        let mut s = "a".repeat(ascii);
+        s.push_str(tail);
+        s
+    }
+
+    #[test]
+    fn limit_inside_null_pair() {
+        let s = after_ascii(MAX_UTF_LEN - 1, "\0b");
+        assert_truncated(&s, MAX_UTF_LEN, MAX_UTF_LEN - 1, MAX_UTF_LEN - 1);
+        // The pair fits once the limit is past it.
+        assert_truncated(&s, MAX_UTF_LEN + 1, MAX_UTF_LEN, MAX_UTF_LEN);
+    }
+
+    #[test]
+    fn limit_inside_three_byte_char() {
+        for ascii in [MAX_UTF_LEN - 2, MAX_UTF_LEN - 1] {
+            let s = after_ascii(ascii, "\u{6F22}");
+            assert_truncated(&s, MAX_UTF_LEN, ascii, ascii);
+        }
+        let s = after_ascii(MAX_UTF_LEN - 3, "\u{6F22}");
+        assert_truncated(&s, MAX_UTF_LEN, s.len(), MAX_UTF_LEN - 2);
+    }
+
+    #[test]
+    fn limit_inside_surrogate_pair() {
+        // A supplementary character takes up 4 bytes in UTF-8, but 6 in
+        // MUTF-8, so it can be cut off even where it would fit as UTF-8.
+        for ascii in MAX_UTF_LEN - 5..MAX_UTF_LEN {
+            let s = after_ascii(ascii, "\u{1F600}");
+            assert_truncated(&s, MAX_UTF_LEN, ascii, ascii);
+        }
+        let s = after_ascii(MAX_UTF_LEN - 6, "\u{1F600}");
+        assert_truncated(&s, MAX_UTF_LEN, s.len(), MAX_UTF_LEN - 5);
+        let (encoded, _) = encode_truncated(&s, MAX_UTF_LEN);
+        assert_eq!(
+            encoded[MAX_UTF_LEN - 6..],
+            [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]
+        );
+    }
+
+    #[test]
+    fn limit_of_zero() {
+        for s in ["", "a", "\0", "\u{E9}", "\u{1F600}"] {
+            assert_eq!(encode_truncated(s, 0), (Cow::Borrowed(&[][..]), 0), "{s:?}");
+            assert_eq!(floor_char_boundary_for_len(s, 0), 0, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn exactly_at_limit() {
+        let s = "a".repeat(MAX_UTF_LEN);
+        assert_eq!(
+            encode_truncated(&s, MAX_UTF_LEN),
+            (Cow::Borrowed(s.as_bytes()), MAX_UTF_LEN)
+        );
+        assert_eq!(floor_char_boundary_for_len(&s, MAX_UTF_LEN), s.len());
+
+        // Shorter than the limit in UTF-8, but exactly at it in MUTF-8.
+        let s = after_ascii(1, &"\0".repeat(MAX_UTF_LEN / 2));
+        assert_truncated(&s, MAX_UTF_LEN, s.len(), 1 + MAX_UTF_LEN / 2);
+        assert_truncated(&s, MAX_UTF_LEN - 1, s.len() - 1, MAX_UTF_LEN / 2);
+
+        let mut data = Vec::new();
+        write_utf(&s, &mut data).unwrap();
+        assert_eq!(data.len(), 2 + MAX_UTF_LEN);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{error::Invalid, Supplementary};
+use alloc::string::String;
+
+/// The longest sequence that decodes to a single character: a surrogate pair.
+const MAX_SEQUENCE_LEN: usize = 6;
+
+/// An incremental MUTF-8 decoder that keeps its state between chunks of input.
+///
+/// `Decoder` is meant for input that arrives in pieces, such as from a network
+/// socket, where blocking on a reader is not an option. It is modeled after the
+/// decoders of the [`encoding_rs`] crate: every call decodes as much of a chunk
+/// of input as fits into a caller-provided output buffer and reports why it
+/// stopped through a [`DecoderResult`]. Nothing is ever allocated.
+///
+/// A sequence split across two chunks, such as a surrogate pair or
+/// `0xC0 0x80`, is held by the decoder until the rest of it arrives. Input is
+/// validated by the same rules as [`decode`](crate::decode), apart from the
+/// initial UTF-8 check. As with `decode`, supplementary characters may be
+/// written either as surrogate pairs or as 4-byte sequences, but not both.
+///
+/// [`encoding_rs`]: https://docs.rs/encoding_rs
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::{Decoder, DecoderResult};
+///
+/// let mut decoder = Decoder::new();
+/// let mut output = [0; 16];
+///
+/// // The surrogate pair is split across both chunks.
+/// let (result, read, written) =
+///     decoder.decode_to_utf8(&[b'a', 0xC0, 0x80, 0xED, 0xA0], &mut output, false);
+/// assert_eq!((result, read, written), (DecoderResult::InputEmpty, 5, 2));
+///
+/// let (result, read, written2) =
+///     decoder.decode_to_utf8(&[0x81, 0xED, 0xB0, 0x81], &mut output[written..], true);
+/// assert_eq!((result, read, written2), (DecoderResult::InputEmpty, 4, 4));
+/// assert_eq!(&output[..written + written2], "a\0\u{10401}".as_bytes());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Decoder {
+    pending: [u8; MAX_SEQUENCE_LEN],
+    pending_len: usize,
+    form: Supplementary,
+}
+
+/// The reason a [`Decoder`] stopped decoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DecoderResult {
+    /// All of the input was consumed, although some of it may be held by the
+    /// decoder until more input arrives.
+    InputEmpty,
+    /// The output buffer is too small to hold the next character. The caller
+    /// should make room and call the decoder again with the rest of the input.
+    OutputFull,
+    /// A malformed sequence was found and skipped.
+    ///
+    /// The first value is the length of the malformed sequence in bytes. The
+    /// second value is how many bytes after it were already consumed, which can
+    /// only be non-zero when the malformed sequence started in an earlier chunk
+    /// of input. Together they tell how far back from the current position the
+    /// malformed sequence is. The caller may choose to emit a replacement
+    /// character before calling the decoder again with the rest of the input.
+    Malformed(u8, u8),
+}
+
+impl Decoder {
+    /// Creates a new `Decoder`.
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Decoder {
+        Decoder {
+            pending: [0; MAX_SEQUENCE_LEN],
+            pending_len: 0,
+            form: Supplementary {
+                four_byte: false,
+                surrogate_pair: false,
+            },
+        }
+    }
+
+    /// Returns the size of the output buffer that is guaranteed to hold
+    /// everything decoded from the next `byte_length` bytes of input, or
+    /// `None` if that size overflows `usize`.
+    #[must_use]
+    #[inline]
+    pub const fn max_utf8_buffer_length(&self, byte_length: usize) -> Option<usize> {
+        // No MUTF-8 sequence is shorter than the UTF-8 one it decodes to.
+        byte_length.checked_add(self.pending_len)
+    }
+
+    /// Decodes a chunk of MUTF-8 input to UTF-8, writing into `dst`.
+    ///
+    /// Returns why decoding stopped, how many bytes of `src` were read and how
+    /// many bytes of `dst` were written. Only whole characters are ever
+    /// written. `last` must be `true` for the final chunk of input, and only
+    /// then is a sequence left incomplete at its end reported as malformed.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::{Decoder, DecoderResult};
+    ///
+    /// let mut decoder = Decoder::new();
+    /// let mut output = [0; 4];
+    ///
+    /// // A lone high surrogate is malformed.
+    /// let input = &[0xED, 0xA0, 0x81, b'a'];
+    /// let (result, read, written) = decoder.decode_to_utf8(input, &mut output, true);
+    /// assert_eq!((result, read, written), (DecoderResult::Malformed(3, 0), 3, 0));
+    ///
+    /// let (result, read, written) = decoder.decode_to_utf8(&input[3..], &mut output, true);
+    /// assert_eq!((result, read, written), (DecoderResult::InputEmpty, 1, 1));
+    /// ```
+    pub fn decode_to_utf8(
+        &mut self,
+        src: &[u8],
+        dst: &mut [u8],
+        last: bool,
+    ) -> (DecoderResult, usize, usize) {
+        let mut read = 0;
+        let mut written = 0;
+
+        if self.pending_len > 0 {
+            let take = (MAX_SEQUENCE_LEN - self.pending_len).min(src.len());
+            let len = self.pending_len + take;
+            let mut bytes = self.pending;
+            bytes[self.pending_len..len].copy_from_slice(&src[..take]);
+
+            match self.form.next_code_point(&bytes[..len]) {
+                Ok((code_point, width)) => {
+                    let Some(char_len) = write_char(code_point, dst) else {
+                        return (DecoderResult::OutputFull, 0, 0);
+                    };
+                    written = char_len;
+                    read = width - self.pending_len;
+                    self.pending_len = 0;
+                }
+                Err(Invalid { len: None, .. }) if !last => {
+                    self.pending = bytes;
+                    self.pending_len = len;
+                    return (DecoderResult::InputEmpty, take, 0);
+                }
+                Err(invalid) => {
+                    let malformed = invalid.len.map_or(len, usize::from);
+                    return if malformed <= self.pending_len {
+                        let after = self.pending_len - malformed;
+                        self.pending.copy_within(malformed..self.pending_len, 0);
+                        self.pending_len = after;
+                        (malformed_result(malformed, after), 0, 0)
+                    } else {
+                        let read = malformed - self.pending_len;
+                        self.pending_len = 0;
+                        (malformed_result(malformed, 0), read, 0)
+                    };
+                }
+            }
+        }
+
+        while read < src.len() {
+            let byte = src[read];
+            if byte != 0 && byte.is_ascii() {
+                if written == dst.len() {
+                    return (DecoderResult::OutputFull, read, written);
+                }
+                dst[written] = byte;
+                written += 1;
+                read += 1;
+                continue;
+            }
+
+            match self.form.next_code_point(&src[read..]) {
+                Ok((code_point, width)) => {
+                    let Some(char_len) = write_char(code_point, &mut dst[written..]) else {
+                        return (DecoderResult::OutputFull, read, written);
+                    };
+                    written += char_len;
+                    read += width;
+                }
+                Err(Invalid { len: None, .. }) if !last => {
+                    let rest = &src[read..];
+                    self.pending[..rest.len()].copy_from_slice(rest);
+                    self.pending_len = rest.len();
+                    return (DecoderResult::InputEmpty, src.len(), written);
+                }
+                Err(invalid) => {
+                    let malformed = invalid.len.map_or(src.len() - read, usize::from);
+                    read += malformed;
+                    return (malformed_result(malformed, 0), read, written);
+                }
+            }
+        }
+
+        (DecoderResult::InputEmpty, read, written)
+    }
+
+    /// Decodes a chunk of MUTF-8 input, appending it to `dst`.
+    ///
+    /// This works like [`decode_to_utf8`](Decoder::decode_to_utf8), except that
+    /// it writes into the spare capacity of a [`String`] and returns only how
+    /// many bytes of `src` were read. The string is never reallocated; once its
+    /// capacity is used up, [`DecoderResult::OutputFull`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::{Decoder, DecoderResult};
+    ///
+    /// let mut decoder = Decoder::new();
+    /// let mut output = String::with_capacity(8);
+    /// let input = &[b'a', 0xC0, 0x80];
+    /// let (result, read) = decoder.decode_to_string(input, &mut output, true);
+    /// assert_eq!((result, read), (DecoderResult::InputEmpty, 3));
+    /// assert_eq!(output, "a\0");
+    /// ```
+    pub fn decode_to_string(
+        &mut self,
+        src: &[u8],
+        dst: &mut String,
+        last: bool,
+    ) -> (DecoderResult, usize) {
+        let mut bytes = core::mem::take(dst).into_bytes();
+        let len = bytes.len();
+        // Only as much of the spare capacity as can be written to is zeroed.
+        let spare = self
+            .max_utf8_buffer_length(src.len())
+            .unwrap_or(usize::MAX)
+            .min(bytes.capacity() - len);
+        bytes.resize(len + spare, 0);
+        let (result, read, written) = self.decode_to_utf8(src, &mut bytes[len..], last);
+        bytes.truncate(len + written);
+        // SAFETY: `decode_to_utf8` only ever writes whole UTF-8 characters.
+        *dst = unsafe { String::from_utf8_unchecked(bytes) };
+        (result, read)
+    }
+}
+
+/// Writes the UTF-8 representation of a code point to the start of `dst`,
+/// returning its length, or `None` if it does not fit.
+#[inline]
+fn write_char(code_point: u32, dst: &mut [u8]) -> Option<usize> {
+    // SAFETY: `next_code_point` never returns a surrogate.
+    let c = unsafe { char::from_u32_unchecked(code_point) };
+    let len = c.len_utf8();
+    if len > dst.len() {
+        return None;
+    }
+    c.encode_utf8(dst);
+    Some(len)
+}
+
+/// Returns the result for a malformed sequence of `len` bytes, after which
+/// `after` more bytes were already consumed.
+#[inline]
+// A malformed sequence is never longer than `MAX_SEQUENCE_LEN` bytes.
+#[allow(clippy::cast_possible_truncation)]
+fn malformed_result(len: usize, after: usize) -> DecoderResult {
+    debug_assert!(len <= MAX_SEQUENCE_LEN && after <= MAX_SEQUENCE_LEN);
+    DecoderResult::Malformed(len as u8, after as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    #[test]
+    fn decode_to_string_keeps_buffer() {
+        let mut decoder = Decoder::new();
+        let mut output = String::with_capacity(64);
+        output.push('x');
+        let ptr = output.as_ptr();
+
+        let (result, read) = decoder.decode_to_string(&[b'a', 0xC0, 0x80], &mut output, true);
+        assert_eq!((result, read), (DecoderResult::InputEmpty, 3));
+        assert_eq!(output, "xa\0");
+        assert_eq!(output.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn decode_to_string_across_chunks() {
+        let mut decoder = Decoder::new();
+        let mut output = String::with_capacity(8);
+
+        // A surrogate pair split across two chunks decodes to more bytes than
+        // the second chunk holds.
+        let (result, read) =
+            decoder.decode_to_string(&[b'a', 0xED, 0xA0, 0xBD], &mut output, false);
+        assert_eq!((result, read), (DecoderResult::InputEmpty, 4));
+        let (result, read) = decoder.decode_to_string(&[0xED, 0xB8, 0x80], &mut output, true);
+        assert_eq!((result, read), (DecoderResult::InputEmpty, 3));
+        assert_eq!(output, "a\u{1F600}");
+
+        // Only whole characters fit into the remaining capacity.
+        let ptr = output.as_ptr();
+        let fit = (output.capacity() - output.len()) / 2;
+        let input = [0xC3, 0xA9].repeat(fit + 1);
+        let (result, read) = decoder.decode_to_string(&input, &mut output, true);
+        assert_eq!((result, read), (DecoderResult::OutputFull, fit * 2));
+        assert_eq!(output.len(), 5 + fit * 2);
+        assert_eq!(output.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn four_byte_sequence_across_chunks() {
+        let mut decoder = Decoder::new();
+        let mut output = [0; 16];
+
+        let input = [0xC0, 0x80, 0xF0, 0x9F, 0x98, 0x80, b'a'];
+        assert_eq!(crate::decode(&input).as_deref(), Ok("\0\u{1F600}a"));
+        let (result, read, written) = decoder.decode_to_utf8(&input[..4], &mut output, false);
+        assert_eq!((result, read, written), (DecoderResult::InputEmpty, 4, 1));
+        let (result, read, written2) =
+            decoder.decode_to_utf8(&input[4..], &mut output[written..], true);
+        assert_eq!((result, read, written2), (DecoderResult::InputEmpty, 3, 5));
+        assert_eq!(&output[..written + written2], "\0\u{1F600}a".as_bytes());
+
+        // Once a 4-byte sequence was seen, a surrogate pair is rejected.
+        let input = [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+        assert!(
+            crate::decode(&[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]).is_err()
+        );
+        let (result, read, written) = decoder.decode_to_utf8(&input[..2], &mut output, false);
+        assert_eq!((result, read, written), (DecoderResult::InputEmpty, 2, 0));
+        let (result, read, written) = decoder.decode_to_utf8(&input[2..], &mut output, true);
+        assert_eq!(
+            (result, read, written),
+            (DecoderResult::Malformed(6, 0), 4, 0)
+        );
+    }
+
+    #[test]
+    fn four_byte_sequence_after_surrogate_pair() {
+        let mut decoder = Decoder::new();
+        let mut output = [0; 16];
+
+        let input = [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xF0, 0x9F, 0x98, 0x80];
+        assert!(crate::decode(&input).is_err());
+        let (result, read, written) = decoder.decode_to_utf8(&input, &mut output, true);
+        assert_eq!(
+            (result, read, written),
+            (DecoderResult::Malformed(4, 0), 10, 4)
+        );
+        assert_eq!(&output[..written], "\u{1F600}".as_bytes());
+    }
+}
@@ -0,0 +1,334 @@
+//! Reading and writing the strings of an Android DEX file.
+//!
+//! A DEX file stores each of its strings as a `string_data_item`: the length
+//! of the string in UTF-16 code units as a ULEB128, followed by the MUTF-8
+//! bytes of the string and a terminating null byte.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use mutf8::dex;
+//!
+//! let mut item = Vec::new();
+//! dex::write_string_data_item("a\0\u{10401}", &mut item);
+//! assert_eq!(item, &[0x04, b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, 0x00]);
+//!
+//! let (str, read) = dex::read_string_data_item(&item).unwrap();
+//! assert_eq!(str, "a\0\u{10401}");
+//! assert_eq!(read, item.len());
+//! ```
+
+use crate::{decode, encode, Error};
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+/// Reads a `string_data_item` from the start of a slice of bytes.
+///
+/// The MUTF-8 bytes of the string are decoded by the same rules as [`decode`].
+/// On success, the decoded string is returned along with the total number of
+/// bytes the item takes up, terminating null byte included.
+///
+/// # Errors
+///
+/// Returns [`DexStringError`] if the item is malformed, which includes the case
+/// where the declared length of the string does not match its content. As the
+/// string ends at the null byte, a sequence cut short by it is reported as
+/// invalid, so [`error_len`](Error::error_len) is never `None`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::dex::{self, DexStringError};
+///
+/// let (str, read) = dex::read_string_data_item(&[0x02, b'h', b'i', 0x00, 0xFF]).unwrap();
+/// assert_eq!((&*str, read), ("hi", 4));
+///
+/// let error = dex::read_string_data_item(&[0x03, b'h', b'i', 0x00]).unwrap_err();
+/// assert_eq!(error, DexStringError::SizeMismatch { declared: 3, actual: 2 });
+/// ```
+pub fn read_string_data_item(bytes: &[u8]) -> Result<(Cow<'_, str>, usize), DexStringError> {
+    let (declared, start) = read_uleb128(bytes)?;
+    let Some(len) = bytes[start..].iter().position(|&byte| byte == 0) else {
+        return Err(DexStringError::UnexpectedEnd);
+    };
+    let data = &bytes[start..start + len];
+
+    let decoded = decode(data).map_err(|error| {
+        // The string cannot go on past the null byte, so a sequence cut short
+        // by it is invalid rather than incomplete. Decoding the null byte along
+        // with it reports it like any other byte that cannot continue it.
+        let error = match error.error_len() {
+            Some(_) => error,
+            None => decode(&bytes[start..=start + len]).unwrap_err(),
+        };
+        DexStringError::Mutf8(error.offset(start))
+    })?;
+    let actual = decoded.encode_utf16().count();
+    if actual != declared as usize {
+        return Err(DexStringError::SizeMismatch { declared, actual });
+    }
+    Ok((decoded, start + len + 1))
+}
+
+/// Writes a string as a `string_data_item`.
+///
+/// The string is encoded by [`encode`] and appended to `out`, preceded by its
+/// length in UTF-16 code units as a ULEB128 and followed by a null byte.
+///
+/// # Panics
+///
+/// Panics if the length of the string in UTF-16 code units does not fit in a
+/// `u32`, which a DEX file cannot represent.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mut item = Vec::new();
+/// mutf8::dex::write_string_data_item("\u{E9}", &mut item);
+/// assert_eq!(item, &[0x01, 0xC3, 0xA9, 0x00]);
+/// ```
+pub fn write_string_data_item(s: &str, out: &mut Vec<u8>) {
+    let Ok(mut size) = u32::try_from(s.encode_utf16().count()) else {
+        panic!("string too long for a DEX string_data_item");
+    };
+    while size >= 0x80 {
+        out.push(size.to_le_bytes()[0] | 0x80);
+        size >>= 7;
+    }
+    out.push(size.to_le_bytes()[0]);
+    out.extend_from_slice(&encode(s));
+    out.push(0);
+}
+
+/// Reads the ULEB128 at the start of `bytes`, returning its value along with
+/// the number of bytes it takes up.
+fn read_uleb128(bytes: &[u8]) -> Result<(u32, usize), DexStringError> {
+    let mut value = 0;
+    for (index, &byte) in bytes.iter().enumerate().take(5) {
+        if index == 4 && byte > 0x0F {
+            return Err(DexStringError::InvalidSize);
+        }
+        value |= u32::from(byte & 0x7F) << (7 * index);
+        if byte < 0x80 {
+            return Ok((value, index + 1));
+        }
+    }
+    if bytes.len() < 5 {
+        Err(DexStringError::UnexpectedEnd)
+    } else {
+        Err(DexStringError::InvalidSize)
+    }
+}
+
+/// An error thrown when a `string_data_item` is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DexStringError {
+    /// The ULEB128 length of the string does not fit in a `u32`.
+    InvalidSize,
+    /// The input ended before the end of the item.
+    UnexpectedEnd,
+    /// The string is invalid MUTF-8 data. The position given by
+    /// [`valid_up_to`](Error::valid_up_to) is relative to the start of the
+    /// item.
+    Mutf8(Error),
+    /// The declared length of the string does not match its content.
+    SizeMismatch {
+        /// The length of the string in UTF-16 code units, as declared by the
+        /// item.
+        declared: u32,
+        /// The length of the decoded string in UTF-16 code units.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for DexStringError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DexStringError::InvalidSize => f.write_str("invalid ULEB128 string length"),
+            DexStringError::UnexpectedEnd => f.write_str("unexpected end of string_data_item"),
+            DexStringError::Mutf8(error) => error.fmt(f),
+            DexStringError::SizeMismatch { declared, actual } => write!(
+                f,
+                "string declared as {declared} UTF-16 code units, but {actual} were found",
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for DexStringError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DexStringError::Mutf8(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use alloc::vec;
+
+    #[test]
+    fn multi_byte_size() {
+        for (len, size) in [
+            (127, &[0x7F][..]),
+            (128, &[0x80, 0x01]),
+            (300, &[0xAC, 0x02]),
+            (16_384, &[0x80, 0x80, 0x01]),
+        ] {
+            let s = "a".repeat(len);
+            let mut item = Vec::new();
+            write_string_data_item(&s, &mut item);
+            assert_eq!(&item[..size.len()], size, "{len}");
+            assert_eq!(item.len(), size.len() + len + 1, "{len}");
+            assert_eq!(
+                read_string_data_item(&item),
+                Ok((Cow::Borrowed(&*s), item.len())),
+            );
+        }
+
+        // A supplementary character counts as 2 units, and takes up 6 bytes.
+        let s = "\u{1F600}".repeat(64);
+        let mut item = Vec::new();
+        write_string_data_item(&s, &mut item);
+        assert_eq!(&item[..2], &[0x80, 0x01]);
+        assert_eq!(item.len(), 2 + 64 * 6 + 1);
+        assert_eq!(read_string_data_item(&item).unwrap().0, s);
+    }
+
+    #[test]
+    fn invalid_size() {
+        for bytes in [
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x00][..],
+            &[0x80, 0x80, 0x80, 0x80, 0x10, 0x00],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00],
+        ] {
+            assert_eq!(
+                read_string_data_item(bytes),
+                Err(DexStringError::InvalidSize),
+                "{bytes:02X?}",
+            );
+        }
+
+        // The largest size that fits in 5 bytes is read, but then does not
+        // match the content.
+        assert_eq!(
+            read_string_data_item(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x00]),
+            Err(DexStringError::SizeMismatch {
+                declared: u32::MAX,
+                actual: 0
+            }),
+        );
+
+        for bytes in [&[][..], &[0x80], &[0xFF, 0xFF, 0xFF, 0xFF]] {
+            assert_eq!(
+                read_string_data_item(bytes),
+                Err(DexStringError::UnexpectedEnd),
+                "{bytes:02X?}",
+            );
+        }
+    }
+
+    #[test]
+    fn size_mismatch() {
+        // `U+10401` is 2 UTF-16 code units, not 1.
+        let item = [0x01, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, 0x00];
+        assert_eq!(
+            read_string_data_item(&item),
+            Err(DexStringError::SizeMismatch {
+                declared: 1,
+                actual: 2
+            }),
+        );
+        let item = [0x03, b'a', 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, 0x00];
+        assert_eq!(
+            read_string_data_item(&item),
+            Ok((Cow::Owned("a\u{10401}".into()), item.len())),
+        );
+
+        // A null pair is 1 unit, not 2.
+        let item = [0x02, 0xC0, 0x80, 0x00];
+        assert_eq!(
+            read_string_data_item(&item),
+            Err(DexStringError::SizeMismatch {
+                declared: 2,
+                actual: 1
+            }),
+        );
+    }
+
+    #[test]
+    fn missing_null_byte() {
+        for bytes in [&[0x00][..], &[0x02, b'h', b'i'], &[0x01, 0xC0, 0x80]] {
+            assert_eq!(
+                read_string_data_item(bytes),
+                Err(DexStringError::UnexpectedEnd),
+                "{bytes:02X?}",
+            );
+        }
+    }
+
+    #[test]
+    fn lone_surrogate() {
+        for (bytes, valid_up_to) in [
+            (vec![0x03, b'a', 0xED, 0xA0, 0x81, b'b', 0x00], 2),
+            (vec![0x01, 0xED, 0xB0, 0x81, 0x00], 1),
+            (vec![0x80, 0x01, 0xED, 0xB0, 0x81, 0x00], 2),
+        ] {
+            let Err(DexStringError::Mutf8(error)) = read_string_data_item(&bytes) else {
+                panic!("{bytes:02X?}");
+            };
+            assert_eq!(error.kind(), ErrorKind::UnpairedSurrogate, "{bytes:02X?}");
+            assert_eq!(error.valid_up_to(), valid_up_to, "{bytes:02X?}");
+            assert_eq!(error.error_len(), Some(3), "{bytes:02X?}");
+        }
+
+        // A high surrogate right before the null byte cannot be completed, so
+        // it is unpaired rather than incomplete.
+        for item in [
+            &[0x02, b'a', 0xED, 0xA0, 0x81, 0x00][..],
+            &[0x02, b'a', 0xED, 0xA0, 0x81, 0xED, 0x00],
+            &[0x02, b'a', 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x00],
+        ] {
+            let Err(DexStringError::Mutf8(error)) = read_string_data_item(item) else {
+                panic!("{item:02X?}");
+            };
+            assert_eq!(error.kind(), ErrorKind::UnpairedSurrogate, "{item:02X?}");
+            assert_eq!((error.valid_up_to(), error.error_len()), (2, Some(3)));
+        }
+    }
+
+    #[test]
+    fn sequence_cut_short_by_null_byte() {
+        for (item, kind, error_len) in [
+            (&[0x02, b'a', 0xC0, 0x00][..], ErrorKind::InvalidNullPair, 1),
+            (&[0x02, b'a', 0xC3, 0x00], ErrorKind::InvalidContinuation, 1),
+            (
+                &[0x02, b'a', 0xE6, 0xBC, 0x00],
+                ErrorKind::InvalidContinuation,
+                2,
+            ),
+            (&[0x02, b'a', 0xED, 0x00], ErrorKind::InvalidContinuation, 1),
+        ] {
+            let Err(DexStringError::Mutf8(error)) = read_string_data_item(item) else {
+                panic!("{item:02X?}");
+            };
+            assert_eq!(error.kind(), kind, "{item:02X?}");
+            assert_eq!(error.valid_up_to(), 2, "{item:02X?}");
+            assert_eq!(error.error_len(), Some(error_len), "{item:02X?}");
+        }
+    }
+}
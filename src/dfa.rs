@@ -0,0 +1,257 @@
+use crate::{
+    error::Error,
+    scan,
+    simd::{self, ByteSet},
+};
+
+// The states of the automaton. Every state other than `ACCEPT` and `REJECT`
+// is partway through a sequence, and names what is expected next.
+const ACCEPT: u8 = 0;
+const REJECT: u8 = 1;
+/// The second byte of a null pair, `0x80`.
+const NULL_PAIR: u8 = 2;
+/// The last continuation byte of a sequence.
+const CONT_1: u8 = 3;
+/// The second byte of a 3-byte sequence starting with `0xE0`, which must not
+/// be overlong.
+const E0: u8 = 4;
+/// The second-to-last continuation byte of a 3-byte sequence.
+const CONT_2: u8 = 5;
+/// The second byte of a 3-byte sequence starting with `0xED`, which decides
+/// whether it is a surrogate.
+const ED: u8 = 6;
+/// The last continuation byte of a high surrogate.
+const HIGH_CONT: u8 = 7;
+/// The first byte of the low surrogate following a high surrogate, `0xED`.
+const LOW_ED: u8 = 8;
+/// The second byte of the low surrogate following a high surrogate.
+const LOW: u8 = 9;
+const STATES: u8 = 10;
+
+// The classes of bytes that the automaton tells apart.
+const NULL: u8 = 0;
+const ASCII: u8 = 1;
+const X80: u8 = 2;
+const X81_8F: u8 = 3;
+const X90_9F: u8 = 4;
+const XA0_AF: u8 = 5;
+const XB0_BF: u8 = 6;
+const XC0: u8 = 7;
+const XC1: u8 = 8;
+const XC2_DF: u8 = 9;
+const XE0: u8 = 10;
+const XE1_EF: u8 = 11;
+const XED: u8 = 12;
+const XF0_FF: u8 = 13;
+const CLASSES: u8 = 14;
+
+/// The class of every byte.
+const CLASS: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut byte: u8 = 0;
+    loop {
+        table[byte as usize] = match byte {
+            0x00 => NULL,
+            0x01..=0x7F => ASCII,
+            0x80 => X80,
+            0x81..=0x8F => X81_8F,
+            0x90..=0x9F => X90_9F,
+            0xA0..=0xAF => XA0_AF,
+            0xB0..=0xBF => XB0_BF,
+            0xC0 => XC0,
+            0xC1 => XC1,
+            0xC2..=0xDF => XC2_DF,
+            0xE0 => XE0,
+            0xED => XED,
+            0xE1..=0xEF => XE1_EF,
+            0xF0..=0xFF => XF0_FF,
+        };
+        if byte == u8::MAX {
+            break table;
+        }
+        byte += 1;
+    }
+};
+
+/// The state that follows each state on each class of byte, indexed by
+/// `state + class`. States are stored multiplied by `CLASSES`, so that they
+/// can be used as an index as they are.
+const TRANSITION: [u8; STATES as usize * CLASSES as usize] = {
+    let mut table = [0; STATES as usize * CLASSES as usize];
+    let mut state = 0;
+    while state < STATES {
+        let mut class = 0;
+        while class < CLASSES {
+            table[(state * CLASSES + class) as usize] = transition(state, class) * CLASSES;
+            class += 1;
+        }
+        state += 1;
+    }
+    table
+};
+
+const fn transition(state: u8, class: u8) -> u8 {
+    match (state, class) {
+        (ACCEPT, ASCII) | (NULL_PAIR, X80) | (CONT_1, X80..=XB0_BF) => ACCEPT,
+        (ACCEPT, XC0) => NULL_PAIR,
+        (ACCEPT, XC2_DF)
+        | (E0, XA0_AF | XB0_BF)
+        | (CONT_2, X80..=XB0_BF)
+        | (ED, X80..=X90_9F)
+        | (LOW, XB0_BF) => CONT_1,
+        (ACCEPT, XE0) => E0,
+        (ACCEPT, XE1_EF) => CONT_2,
+        (ACCEPT, XED) => ED,
+        (ED, XA0_AF) => HIGH_CONT,
+        (HIGH_CONT, X80..=XB0_BF) => LOW_ED,
+        (LOW_ED, XED) => LOW,
+        _ => REJECT,
+    }
+}
+
+/// Returns the state that follows `state` on `byte`.
+#[inline]
+const fn next(state: usize, byte: u8) -> usize {
+    TRANSITION[state + CLASS[byte as usize] as usize] as usize
+}
+
+/// Runs the automaton over `bytes`, returning the index at which the first
+/// invalid sequence starts, if any.
+#[inline]
+pub(crate) const fn run(bytes: &[u8]) -> Result<(), usize> {
+    let mut state = ACCEPT as usize;
+    let mut index = 0;
+    let mut valid_up_to = 0;
+    while index < bytes.len() {
+        state = next(state, bytes[index]);
+        index += 1;
+        if state == ACCEPT as usize {
+            valid_up_to = index;
+        } else if state == (REJECT * CLASSES) as usize {
+            return Err(valid_up_to);
+        }
+    }
+    if state == ACCEPT as usize {
+        Ok(())
+    } else {
+        Err(valid_up_to)
+    }
+}
+
+/// Runs the automaton over `bytes` like `run`, but skips every run of non-null
+/// ASCII that follows a complete sequence with a vectorized search.
+#[inline]
+fn run_skipping_ascii(bytes: &[u8]) -> Result<(), usize> {
+    let mut state = ACCEPT as usize;
+    let mut index = 0;
+    let mut valid_up_to = 0;
+    while index < bytes.len() {
+        if state == ACCEPT as usize && matches!(bytes[index], 0x01..=0x7F) {
+            let rest = &bytes[index..];
+            index += simd::find(rest, ByteSet::NON_ASCII).unwrap_or(rest.len());
+            valid_up_to = index;
+            continue;
+        }
+        state = next(state, bytes[index]);
+        index += 1;
+        if state == ACCEPT as usize {
+            valid_up_to = index;
+        } else if state == (REJECT * CLASSES) as usize {
+            return Err(valid_up_to);
+        }
+    }
+    if state == ACCEPT as usize {
+        Ok(())
+    } else {
+        Err(valid_up_to)
+    }
+}
+
+/// Checks that a slice of bytes is valid MUTF-8, without decoding it.
+///
+/// Only the canonical form written by the JVM is accepted, the same as by
+/// [`MStr::from_bytes`](crate::MStr::from_bytes) and
+/// [`decode_strict`](crate::decode_strict): the input must not contain a raw
+/// null byte, a 4-byte UTF-8 sequence, or a surrogate that is not part of a
+/// surrogate pair, and `0xC0 0x80` is the only overlong sequence allowed.
+///
+/// This is useful when the bytes only need to be checked, such as when
+/// verifying a class file, as nothing is allocated.
+///
+/// # Errors
+///
+/// Returns [`Error`] describing the first invalid sequence, exactly as
+/// [`decode_strict`](crate::decode_strict) would.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::ErrorKind;
+///
+/// assert!(mutf8::validate(&[b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]).is_ok());
+///
+/// let error = mutf8::validate(&[b'a', 0x00]).unwrap_err();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(error.kind(), ErrorKind::NullByte);
+///
+/// let error = mutf8::validate(&[b'a', 0xED, 0xA0, 0x81]).unwrap_err();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(error.error_len(), None);
+/// ```
+#[inline]
+pub fn validate(bytes: &[u8]) -> Result<(), Error> {
+    run_skipping_ascii(bytes).map_err(|valid_up_to| describe_error(bytes, valid_up_to))
+}
+
+/// Describes the invalid sequence the automaton stopped at.
+#[inline(never)]
+#[cold]
+fn describe_error(bytes: &[u8], valid_up_to: usize) -> Error {
+    match scan::next_code_point(&bytes[valid_up_to..], true) {
+        Err(invalid) => Error::new(valid_up_to, invalid),
+        Ok(_) => unreachable!("MUTF-8 data was rejected without an invalid sequence"),
+    }
+}
+
+/// Returns `true` if a slice of bytes is valid MUTF-8.
+///
+/// This accepts the same input as [`validate`], but can be used in a constant
+/// context.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// const VALID: bool = mutf8::is_valid_mutf8(&[b'a', 0xC0, 0x80]);
+/// assert!(VALID);
+///
+/// // A raw null byte is valid UTF-8, but it is not valid MUTF-8.
+/// assert!(!mutf8::is_valid_mutf8(&[b'a', 0x00]));
+///
+/// // A 4-byte UTF-8 character must be written as a surrogate pair.
+/// assert!(!mutf8::is_valid_mutf8("\u{10401}".as_bytes()));
+/// assert!(mutf8::is_valid_mutf8(&[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]));
+/// ```
+#[must_use]
+#[inline]
+pub const fn is_valid_mutf8(bytes: &[u8]) -> bool {
+    run(bytes).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_valid_mutf8, validate};
+    use crate::{decode_strict, tests::inputs};
+
+    #[test]
+    fn validate_matches_decode_strict() {
+        inputs(1_000_000, |bytes| {
+            let expected = decode_strict(bytes).map(drop);
+            assert_eq!(validate(bytes), expected, "{bytes:02X?}");
+            assert_eq!(is_valid_mutf8(bytes), expected.is_ok(), "{bytes:02X?}");
+        });
+    }
+}
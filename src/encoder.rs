@@ -0,0 +1,214 @@
+use crate::{utf16::encode_supplementary, NULL_PAIR};
+use alloc::vec::Vec;
+
+/// An incremental MUTF-8 encoder that writes into bounded output buffers.
+///
+/// `Encoder` is the counterpart to [`Decoder`](crate::Decoder). Every call
+/// encodes as much of a string slice as fits into a caller-provided output
+/// buffer and reports why it stopped through an [`EncoderResult`], so MUTF-8
+/// can be written into fixed-size buffers, such as those of a packet writer,
+/// without ever allocating.
+///
+/// A character is only ever written as a whole: a supplementary character is
+/// never split between the two halves of its surrogate pair, and a null
+/// character is never split between the two bytes of `0xC0 0x80`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::{Encoder, EncoderResult};
+///
+/// let mut encoder = Encoder::new();
+/// let mut output = [0; 4];
+///
+/// // The surrogate pair does not fit after the null pair, so it is left for
+/// // the next call.
+/// let (result, read, written) = encoder.encode_from_utf8("\0\u{10401}", &mut output, true);
+/// assert_eq!((result, read, written), (EncoderResult::OutputFull, 1, 2));
+/// assert_eq!(&output[..written], &[0xC0, 0x80]);
+///
+/// let mut output = [0; 6];
+/// let (result, read, written) = encoder.encode_from_utf8("\u{10401}", &mut output, true);
+/// assert_eq!((result, read, written), (EncoderResult::InputEmpty, 4, 6));
+/// assert_eq!(&output, &[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    _private: (),
+}
+
+/// The reason an [`Encoder`] stopped encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EncoderResult {
+    /// All of the input was consumed.
+    InputEmpty,
+    /// The output buffer is too small to hold the next character. The caller
+    /// should make room and call the encoder again with the rest of the input.
+    OutputFull,
+}
+
+impl Encoder {
+    /// Creates a new `Encoder`.
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Encoder {
+        Encoder { _private: () }
+    }
+
+    /// Returns the size of the output buffer that is guaranteed to hold
+    /// everything encoded from the next `byte_length` bytes of UTF-8 input, or
+    /// `None` if that size overflows `usize`.
+    #[must_use]
+    #[inline]
+    pub const fn max_buffer_length_from_utf8(&self, byte_length: usize) -> Option<usize> {
+        // A null character doubles in size, and nothing else grows more.
+        byte_length.checked_mul(2)
+    }
+
+    /// Encodes a string slice to MUTF-8, writing into `dst`.
+    ///
+    /// Returns why encoding stopped, how many bytes of `src` were read and how
+    /// many bytes of `dst` were written. The number of bytes read is always at
+    /// a character boundary of `src`.
+    ///
+    /// `last` tells whether `src` is the final chunk of input. Since a string
+    /// slice never ends in the middle of a character, the encoder never has to
+    /// hold anything back, and the result is the same either way.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::{Encoder, EncoderResult};
+    ///
+    /// let mut encoder = Encoder::new();
+    /// let mut output = [0; 16];
+    /// let (result, read, written) = encoder.encode_from_utf8("a\0b", &mut output, true);
+    /// assert_eq!((result, read, written), (EncoderResult::InputEmpty, 3, 4));
+    /// assert_eq!(&output[..written], &[b'a', 0xC0, 0x80, b'b']);
+    /// ```
+    pub fn encode_from_utf8(
+        &mut self,
+        src: &str,
+        dst: &mut [u8],
+        last: bool,
+    ) -> (EncoderResult, usize, usize) {
+        let _ = last;
+        let bytes = src.as_bytes();
+        let mut read = 0;
+        let mut written = 0;
+
+        while read < bytes.len() {
+            let byte = bytes[read];
+            if byte != 0 && byte.is_ascii() {
+                if written == dst.len() {
+                    return (EncoderResult::OutputFull, read, written);
+                }
+                dst[written] = byte;
+                written += 1;
+                read += 1;
+                continue;
+            }
+
+            let Some(c) = src[read..].chars().next() else {
+                break;
+            };
+            let width = c.len_utf8();
+            let surrogate_pair;
+            let encoded: &[u8] = if c == '\0' {
+                &NULL_PAIR
+            } else if width == 4 {
+                surrogate_pair = encode_supplementary(u32::from(c));
+                &surrogate_pair
+            } else {
+                &bytes[read..read + width]
+            };
+            let Some(dst) = dst.get_mut(written..written + encoded.len()) else {
+                return (EncoderResult::OutputFull, read, written);
+            };
+            dst.copy_from_slice(encoded);
+            written += encoded.len();
+            read += width;
+        }
+
+        (EncoderResult::InputEmpty, read, written)
+    }
+
+    /// Encodes a string slice to MUTF-8, appending it to `dst`.
+    ///
+    /// This works like [`encode_from_utf8`](Encoder::encode_from_utf8), except
+    /// that it writes into the spare capacity of a [`Vec`] and returns only how
+    /// many bytes of `src` were read. The vector is never reallocated; once its
+    /// capacity is used up, [`EncoderResult::OutputFull`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::{Encoder, EncoderResult};
+    ///
+    /// let mut encoder = Encoder::new();
+    /// let mut output = Vec::with_capacity(2);
+    /// let (result, read) = encoder.encode_from_utf8_to_vec("a\0", &mut output, true);
+    /// assert_eq!((result, read), (EncoderResult::OutputFull, 1));
+    /// assert_eq!(output, b"a");
+    /// ```
+    pub fn encode_from_utf8_to_vec(
+        &mut self,
+        src: &str,
+        dst: &mut Vec<u8>,
+        last: bool,
+    ) -> (EncoderResult, usize) {
+        let len = dst.len();
+        // Only as much of the spare capacity as can be written to is zeroed.
+        let spare = self
+            .max_buffer_length_from_utf8(src.len())
+            .unwrap_or(usize::MAX)
+            .min(dst.capacity() - len);
+        dst.resize(len + spare, 0);
+        let (result, read, written) = self.encode_from_utf8(src, &mut dst[len..], last);
+        dst.truncate(len + written);
+        (result, read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_from_utf8_to_vec_keeps_buffer() {
+        let mut encoder = Encoder::new();
+        let mut output = Vec::with_capacity(64);
+        output.push(b'x');
+        let ptr = output.as_ptr();
+
+        let (result, read) = encoder.encode_from_utf8_to_vec("a\0\u{1F600}", &mut output, true);
+        assert_eq!((result, read), (EncoderResult::InputEmpty, 6));
+        assert_eq!(
+            output,
+            [b'x', b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+        );
+        assert!(output.capacity() >= 64);
+        assert_eq!(output.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn encode_from_utf8_to_vec_output_full() {
+        let mut encoder = Encoder::new();
+        let mut output = Vec::with_capacity(6);
+        output.resize(output.capacity() - 4, b'x');
+        let ptr = output.as_ptr();
+
+        // Only whole characters fit into the remaining capacity.
+        let (result, read) = encoder.encode_from_utf8_to_vec("ab\u{1F600}", &mut output, true);
+        assert_eq!((result, read), (EncoderResult::OutputFull, 2));
+        assert!(output.ends_with(b"xab"));
+        assert_eq!(output.len(), output.capacity() - 2);
+        assert_eq!(output.as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,194 @@
+use core::fmt;
+
+/// An error thrown by [`decode`](crate::decode) when the input is invalid
+/// MUTF-8 data.
+///
+/// Much like [`core::str::Utf8Error`], an `Error` describes where the first
+/// invalid sequence was found, how long it is and, through [`ErrorKind`], why
+/// it is invalid.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::ErrorKind;
+///
+/// // A lone high surrogate followed by an ASCII character.
+/// let error = mutf8::decode(&[b'a', 0xED, 0xA0, 0x81, b'b']).unwrap_err();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(error.error_len(), Some(3));
+/// assert_eq!(error.kind(), ErrorKind::UnpairedSurrogate);
+///
+/// // The first half of a null pair at the end of the input.
+/// let error = mutf8::decode(&[b'a', 0xC0]).unwrap_err();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(error.error_len(), None);
+/// assert_eq!(error.kind(), ErrorKind::UnexpectedEnd);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+    valid_up_to: usize,
+    len: Option<u8>,
+    kind: ErrorKind,
+}
+
+impl Error {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize, invalid: Invalid) -> Error {
+        Error {
+            valid_up_to,
+            len: invalid.len,
+            kind: invalid.kind,
+        }
+    }
+
+    /// Moves the position of this error `by` bytes further into the input.
+    #[inline]
+    pub(crate) const fn offset(self, by: usize) -> Error {
+        Error {
+            valid_up_to: self.valid_up_to + by,
+            ..self
+        }
+    }
+
+    /// Returns the index in the given bytes up to which valid MUTF-8 was
+    /// verified.
+    ///
+    /// It is the maximum index such that decoding `&input[..index]` would
+    /// succeed.
+    #[must_use]
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+
+    /// Provides more information about the failure:
+    ///
+    /// - `None`: the end of the input was reached unexpectedly. This is the
+    ///   only case in which [`kind`](Error::kind) is
+    ///   [`ErrorKind::UnexpectedEnd`]. If a byte stream is being decoded in
+    ///   chunks, this may go away once more bytes are appended.
+    /// - `Some(len)`: an invalid sequence of `len` bytes was found, starting
+    ///   at the index given by [`valid_up_to`](Error::valid_up_to). Decoding
+    ///   may be resumed after it.
+    #[must_use]
+    #[inline]
+    pub const fn error_len(&self) -> Option<usize> {
+        match self.len {
+            Some(len) => Some(len as usize),
+            None => None,
+        }
+    }
+
+    /// Returns the reason the input is invalid.
+    #[must_use]
+    #[inline]
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.len {
+            Some(len) => write!(
+                f,
+                "invalid MUTF-8 sequence of {len} bytes from index {}: {}",
+                self.valid_up_to, self.kind,
+            ),
+            None => write!(
+                f,
+                "incomplete MUTF-8 byte sequence from index {}",
+                self.valid_up_to,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl From<Error> for std::io::Error {
+    /// Converts an [`Error`] to an [`std::io::Error`] of kind
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData).
+    #[inline]
+    fn from(error: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// The reason an [`Error`] was thrown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A byte that can never start a sequence, such as a stray continuation
+    /// byte.
+    InvalidByte,
+    /// A lead byte that is not followed by the continuation bytes it
+    /// requires.
+    InvalidContinuation,
+    /// A `0xC0` byte that is not followed by a continuation byte, that is, the
+    /// first half of a null pair on its own.
+    InvalidNullPair,
+    /// An overlong encoding other than the two-byte null character.
+    Overlong,
+    /// A surrogate code point that is not part of a surrogate pair.
+    UnpairedSurrogate,
+    /// A raw 4-byte UTF-8 sequence, which MUTF-8 encodes as a surrogate pair
+    /// instead.
+    FourByteSequence,
+    /// A raw null byte, which MUTF-8 encodes as `0xC0 0x80` instead.
+    NullByte,
+    /// A surrogate pair written as two 3-byte sequences where a single 4-byte
+    /// sequence is required instead, as in WTF-8 data, or in MUTF-8 data that
+    /// already holds a raw 4-byte sequence.
+    SplitSurrogatePair,
+    /// The input ended in the middle of a sequence.
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ErrorKind {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::InvalidByte => "invalid byte",
+            ErrorKind::InvalidContinuation => "invalid continuation byte",
+            ErrorKind::InvalidNullPair => "invalid null pair",
+            ErrorKind::Overlong => "overlong encoding",
+            ErrorKind::UnpairedSurrogate => "unpaired surrogate",
+            ErrorKind::FourByteSequence => "4-byte sequence",
+            ErrorKind::NullByte => "raw null byte",
+            ErrorKind::SplitSurrogatePair => "split surrogate pair",
+            ErrorKind::UnexpectedEnd => "unexpected end of input",
+        })
+    }
+}
+
+/// An invalid sequence found at some position in the input, without the
+/// position itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Invalid {
+    pub(crate) kind: ErrorKind,
+    pub(crate) len: Option<u8>,
+}
+
+impl Invalid {
+    #[inline]
+    pub(crate) const fn new(kind: ErrorKind, len: u8) -> Invalid {
+        Invalid {
+            kind,
+            len: Some(len),
+        }
+    }
+
+    /// The end of the input was reached in the middle of a sequence.
+    pub(crate) const INCOMPLETE: Invalid = Invalid {
+        kind: ErrorKind::UnexpectedEnd,
+        len: None,
+    };
+}
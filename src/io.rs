@@ -0,0 +1,676 @@
+//! Adapters for decoding and encoding MUTF-8 on the fly with [`std::io`].
+
+use crate::{decode, encode, error::Error, len, Supplementary, TooLong};
+use std::{
+    io::{self, Read, Write},
+    str::from_utf8,
+};
+
+/// The default capacity of the buffer used by [`Mutf8Reader`].
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// The longest sequence that decodes to a single character: a surrogate pair.
+const MAX_SEQUENCE_LEN: usize = 6;
+
+/// A reader that decodes a stream of MUTF-8 bytes to UTF-8.
+///
+/// `Mutf8Reader` wraps a reader of MUTF-8 data and yields the UTF-8 bytes of
+/// the decoded text, so input of any size can be decoded without ever holding
+/// all of it in memory. Input is validated by the same rules as [`decode`],
+/// apart from the initial UTF-8 check, and a sequence split across two reads of
+/// the inner reader, such as a surrogate pair or `0xC0 0x80`, is decoded as if
+/// it were not split. As with `decode`, supplementary characters may be
+/// written either as surrogate pairs or as 4-byte sequences, but not both.
+///
+/// # Errors
+///
+/// When invalid data is found, [`read`](Read::read) returns an [`io::Error`] of
+/// kind [`io::ErrorKind::InvalidData`] that wraps an [`Error`]. The position
+/// reported by [`Error::valid_up_to`] is relative to the start of the stream.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::io::Mutf8Reader;
+/// use std::io::Read;
+///
+/// let mutf8_data: &[u8] = &[b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81];
+/// let mut reader = Mutf8Reader::new(mutf8_data);
+/// let mut decoded = String::new();
+/// reader.read_to_string(&mut decoded)?;
+/// assert_eq!(decoded, "a\0\u{10401}");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// Invalid data is reported as an error:
+///
+/// ```
+/// use mutf8::{io::Mutf8Reader, ErrorKind};
+/// use std::io::{self, Read};
+///
+/// let mutf8_data: &[u8] = &[b'a', 0xC0, b'b'];
+/// let error = Mutf8Reader::new(mutf8_data).read_to_end(&mut Vec::new()).unwrap_err();
+/// assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+///
+/// let error = error.into_inner().unwrap().downcast::<mutf8::Error>().unwrap();
+/// assert_eq!(error.valid_up_to(), 1);
+/// assert_eq!(error.kind(), ErrorKind::InvalidNullPair);
+/// ```
+#[derive(Debug)]
+pub struct Mutf8Reader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+    offset: usize,
+    eof: bool,
+    pending: [u8; 4],
+    pending_pos: usize,
+    pending_len: usize,
+    form: Supplementary,
+}
+
+impl<R: Read> Mutf8Reader<R> {
+    /// Creates a new `Mutf8Reader` with a default buffer capacity.
+    #[must_use]
+    #[inline]
+    pub fn new(inner: R) -> Mutf8Reader<R> {
+        Mutf8Reader::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a new `Mutf8Reader` that reads up to `capacity` bytes from the
+    /// inner reader at a time.
+    ///
+    /// The capacity is raised to 6 bytes if it is any smaller, so that a
+    /// surrogate pair always fits.
+    #[must_use]
+    #[inline]
+    pub fn with_capacity(capacity: usize, inner: R) -> Mutf8Reader<R> {
+        Mutf8Reader {
+            inner,
+            buf: vec![0; capacity.max(MAX_SEQUENCE_LEN)].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            offset: 0,
+            eof: false,
+            pending: [0; 4],
+            pending_pos: 0,
+            pending_len: 0,
+            form: Supplementary::default(),
+        }
+    }
+}
+
+impl<R> Mutf8Reader<R> {
+    /// Gets a reference to the underlying reader.
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// It is inadvisable to directly read from the underlying reader.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Mutf8Reader`, returning the underlying reader.
+    ///
+    /// Any buffered data that has not been decoded yet is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decodes buffered input into `out`, returning how many bytes were
+    /// written. Stops early at an incomplete sequence unless the end of the
+    /// input has been reached.
+    fn decode_buffered(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while self.pos < self.filled && written < out.len() {
+            let byte = self.buf[self.pos];
+            if byte != 0 && byte.is_ascii() {
+                out[written] = byte;
+                written += 1;
+                self.pos += 1;
+                self.offset += 1;
+                continue;
+            }
+
+            match self.form.next_code_point(&self.buf[self.pos..self.filled]) {
+                Ok((code_point, width)) => {
+                    // SAFETY: `next_code_point` never returns a surrogate.
+                    let c = unsafe { char::from_u32_unchecked(code_point) };
+                    let encoded = c.encode_utf8(&mut self.pending).len();
+                    let len = encoded.min(out.len() - written);
+                    out[written..written + len].copy_from_slice(&self.pending[..len]);
+                    written += len;
+                    self.pending_pos = len;
+                    self.pending_len = encoded;
+                    self.pos += width;
+                    self.offset += width;
+                }
+                Err(invalid) if invalid.len.is_none() && !self.eof => break,
+                Err(_) if written > 0 => break,
+                Err(invalid) => {
+                    return Err(Error::new(self.offset, invalid).into());
+                }
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+impl<R: Read> Read for Mutf8Reader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+
+        if self.pending_pos < self.pending_len {
+            let pending = &self.pending[self.pending_pos..self.pending_len];
+            let len = pending.len().min(out.len());
+            out[..len].copy_from_slice(&pending[..len]);
+            self.pending_pos += len;
+            return Ok(len);
+        }
+
+        loop {
+            let written = self.decode_buffered(out)?;
+            if written > 0 || (self.eof && self.pos == self.filled) {
+                return Ok(written);
+            }
+
+            self.buf.copy_within(self.pos..self.filled, 0);
+            self.filled -= self.pos;
+            self.pos = 0;
+
+            let read = self.inner.read(&mut self.buf[self.filled..])?;
+            if read == 0 {
+                self.eof = true;
+            }
+            self.filled += read;
+        }
+    }
+}
+
+/// A writer that encodes UTF-8 bytes to MUTF-8 on the fly.
+///
+/// `Mutf8Writer` takes UTF-8 bytes through [`write`](Write::write) and writes
+/// their MUTF-8 representation to the inner writer, so text of any size can be
+/// encoded without ever holding all of it in memory. A null character becomes
+/// `0xC0 0x80` and a supplementary character becomes a surrogate pair, the same
+/// way [`encode`] does it. A character split across two calls to `write` is
+/// held back until it is complete.
+///
+/// Once all data is written, [`finish`](Mutf8Writer::finish) should be called
+/// to check that no incomplete character was left over.
+///
+/// # Errors
+///
+/// When invalid UTF-8 is written, `write` returns an [`io::Error`] of kind
+/// [`io::ErrorKind::InvalidData`] that wraps a [`std::str::Utf8Error`]. Errors
+/// of the inner writer are passed on as-is.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::io::Mutf8Writer;
+/// use std::io::Write;
+///
+/// let mut writer = Mutf8Writer::new(Vec::new());
+/// let utf8_data = "a\0\u{10401}".as_bytes();
+/// // The 4-byte character is split across two calls.
+/// writer.write_all(&utf8_data[..4])?;
+/// writer.write_all(&utf8_data[4..])?;
+/// assert_eq!(
+///     writer.finish()?,
+///     vec![b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81],
+/// );
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// An incomplete character is reported by `finish`:
+///
+/// ```
+/// use mutf8::io::Mutf8Writer;
+/// use std::io::{self, Write};
+///
+/// let mut writer = Mutf8Writer::new(Vec::new());
+/// writer.write_all(&"\u{10401}".as_bytes()[..2])?;
+/// assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Mutf8Writer<W> {
+    inner: W,
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl<W: Write> Mutf8Writer<W> {
+    /// Creates a new `Mutf8Writer`.
+    #[must_use]
+    #[inline]
+    pub fn new(inner: W) -> Mutf8Writer<W> {
+        Mutf8Writer {
+            inner,
+            pending: [0; 4],
+            pending_len: 0,
+        }
+    }
+
+    /// Checks that no incomplete character is left over, flushes the
+    /// underlying writer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] of kind [`io::ErrorKind::InvalidData`] if the
+    /// data written so far ends in the middle of a character, or any error
+    /// returned by the underlying writer while flushing.
+    #[inline]
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Err(error) = from_utf8(&self.pending[..self.pending_len]) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_encoded(&mut self, s: &str) -> io::Result<()> {
+        self.inner.write_all(&encode(s))
+    }
+}
+
+impl<W> Mutf8Writer<W> {
+    /// Gets a reference to the underlying writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for Mutf8Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+
+        if self.pending_len > 0 {
+            let width = utf8_char_width(self.pending[0]);
+            let needed = (width - self.pending_len).min(buf.len());
+            let len = self.pending_len + needed;
+            self.pending[self.pending_len..len].copy_from_slice(&buf[..needed]);
+            let pending = self.pending;
+            match from_utf8(&pending[..len]) {
+                Ok(s) => {
+                    self.write_encoded(s)?;
+                    self.pending_len = 0;
+                    consumed = needed;
+                }
+                Err(error) if error.error_len().is_none() => {
+                    self.pending_len = len;
+                    return Ok(needed);
+                }
+                Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+            }
+        }
+
+        let rest = &buf[consumed..];
+        match from_utf8(rest) {
+            Ok(s) => {
+                self.write_encoded(s)?;
+                consumed = buf.len();
+            }
+            Err(error) => {
+                let valid_up_to = error.valid_up_to();
+                // SAFETY: The bytes up to `valid_up_to` were validated above.
+                self.write_encoded(unsafe { std::str::from_utf8_unchecked(&rest[..valid_up_to]) })?;
+                if error.error_len().is_none() {
+                    let incomplete = &rest[valid_up_to..];
+                    self.pending[..incomplete.len()].copy_from_slice(incomplete);
+                    self.pending_len = incomplete.len();
+                    consumed = buf.len();
+                } else {
+                    consumed += valid_up_to;
+                    if consumed == 0 {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+                    }
+                }
+            }
+        }
+
+        Ok(consumed)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the width of a UTF-8 sequence given its lead byte, which must be
+/// the start of an incomplete but otherwise valid sequence.
+#[inline]
+fn utf8_char_width(byte: u8) -> usize {
+    match byte {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+/// An extension trait for reading strings framed the way
+/// `java.io.DataInput.readUTF` expects them.
+///
+/// This trait is implemented for every [`Read`] type.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::io::ReadUtfExt;
+///
+/// let mut data: &[u8] = &[0x00, 0x03, b'a', 0xC0, 0x80, 0x00, 0x01, b'b'];
+/// assert_eq!(data.read_utf()?, "a\0");
+/// assert_eq!(data.read_utf()?, "b");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait ReadUtfExt: Read {
+    /// Reads a big-endian `u16` length prefix followed by that many bytes of
+    /// MUTF-8, and decodes them by the same rules as [`decode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] of kind [`io::ErrorKind::UnexpectedEof`] if the
+    /// reader ends before the end of the frame, or of kind
+    /// [`io::ErrorKind::InvalidData`] wrapping an [`Error`] if the payload is
+    /// invalid MUTF-8 data. Errors of the reader are passed on as-is.
+    fn read_utf(&mut self) -> io::Result<String> {
+        let mut prefix = [0; 2];
+        self.read_exact(&mut prefix)?;
+        let mut payload = vec![0; usize::from(u16::from_be_bytes(prefix))];
+        self.read_exact(&mut payload)?;
+        match decode(&payload) {
+            Ok(s) => Ok(s.into_owned()),
+            Err(error) => Err(error.offset(2).into()),
+        }
+    }
+}
+
+impl<R: Read + ?Sized> ReadUtfExt for R {}
+
+/// An extension trait for writing strings framed the way
+/// `java.io.DataOutput.writeUTF` writes them.
+///
+/// This trait is implemented for every [`Write`] type.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::io::WriteUtfExt;
+///
+/// let mut data = Vec::new();
+/// data.write_utf("a\0")?;
+/// data.write_utf("b")?;
+/// assert_eq!(data, vec![0x00, 0x03, b'a', 0xC0, 0x80, 0x00, 0x01, b'b']);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait WriteUtfExt: Write {
+    /// Encodes a string by [`encode`] and writes it, preceded by the length of
+    /// its MUTF-8 representation as a big-endian `u16`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] of kind [`io::ErrorKind::InvalidInput`]
+    /// wrapping a [`TooLong`] if the MUTF-8 representation of the string is
+    /// longer than 65535 bytes, in which case nothing is written. Errors of the
+    /// writer are passed on as-is.
+    fn write_utf(&mut self, s: &str) -> io::Result<()> {
+        let encoded_len = len(s);
+        let Ok(prefix) = u16::try_from(encoded_len) else {
+            return Err(TooLong::new(encoded_len).into());
+        };
+        self.write_all(&prefix.to_be_bytes())?;
+        self.write_all(&encode(s))
+    }
+}
+
+impl<W: Write + ?Sized> WriteUtfExt for W {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    /// A reader that returns one byte at a time, splitting every sequence.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), out.first_mut()) {
+                (Some((&byte, rest)), Some(first)) => {
+                    *first = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn read_trickled(bytes: &[u8]) -> Result<String, Error> {
+        let mut decoded = String::new();
+        match Mutf8Reader::new(Trickle(bytes)).read_to_string(&mut decoded) {
+            Ok(_) => Ok(decoded),
+            Err(error) => Err(*error.into_inner().unwrap().downcast::<Error>().unwrap()),
+        }
+    }
+
+    #[test]
+    fn four_byte_sequences_like_decode() {
+        let inputs: [&[u8]; 4] = [
+            &[0xC0, 0x80, 0xF0, 0x9F, 0x98, 0x80, b'a'],
+            &[0xF0, 0x9F, 0x98, 0x80, 0xF0, 0x90, 0x90, 0x81],
+            &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+            &[
+                b'a', 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xF0, 0x9F, 0x98, 0x80,
+            ],
+        ];
+        for input in inputs {
+            let expected = decode(input).map(Cow::into_owned);
+            assert_eq!(read_trickled(input), expected, "{input:X?}");
+            let mut decoded = String::new();
+            let result = Mutf8Reader::new(input).read_to_string(&mut decoded);
+            assert_eq!(result.is_ok(), expected.is_ok(), "{input:X?}");
+        }
+        assert_eq!(
+            read_trickled(&[0xC0, 0x80, 0xF0, 0x9F, 0x98, 0x80, b'a']).as_deref(),
+            Ok("\0\u{1F600}a"),
+        );
+    }
+
+    /// Reads all of `reader` through an output buffer of `out_len` bytes.
+    fn read_in_chunks(mut reader: impl Read, out_len: usize) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        let mut out = vec![0; out_len];
+        loop {
+            match reader.read(&mut out)? {
+                0 => return Ok(decoded),
+                read => decoded.extend_from_slice(&out[..read]),
+            }
+        }
+    }
+
+    #[test]
+    fn reader_splits_sequences_across_reads() {
+        let input = [
+            b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xC3, 0xA9, 0xC0, 0x80, 0xE6,
+            0xBC, 0xA2, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, b'z',
+        ];
+        let expected = "a\0\u{1F600}\u{E9}\0\u{6F22}\u{10401}z";
+        // Every split of the inner buffer of 6 bytes, and of the output.
+        for skip in 0..6 {
+            let mut padded = vec![b'-'; skip];
+            padded.extend_from_slice(&input);
+            let mut expected_padded = "-".repeat(skip);
+            expected_padded.push_str(expected);
+            for out_len in 1..=8 {
+                let reader = Mutf8Reader::with_capacity(0, &padded[..]);
+                let decoded = read_in_chunks(reader, out_len).unwrap();
+                assert_eq!(decoded, expected_padded.as_bytes(), "{skip} {out_len}");
+                let decoded = read_in_chunks(Mutf8Reader::new(Trickle(&padded)), out_len).unwrap();
+                assert_eq!(decoded, expected_padded.as_bytes(), "{skip} {out_len}");
+            }
+        }
+    }
+
+    #[test]
+    fn reader_truncated_at_eof() {
+        for (input, valid_up_to) in [
+            (&[b'a', 0xC0][..], 1),
+            (&[b'a', 0xE6, 0xBC], 1),
+            (&[b'a', 0xED, 0xA0, 0xBD], 1),
+            (&[b'a', 0xED, 0xA0, 0xBD, 0xED, 0xB8], 1),
+            (&[b'a', b'b', b'c', b'd', b'e', b'f', b'g', 0xC0], 7),
+        ] {
+            let expected = decode(input).unwrap_err();
+            assert_eq!(expected.valid_up_to(), valid_up_to, "{input:X?}");
+            assert_eq!(read_trickled(input), Err(expected), "{input:X?}");
+
+            let reader = Mutf8Reader::with_capacity(0, input);
+            let error = read_in_chunks(reader, 1).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{input:X?}");
+            let error = error.into_inner().unwrap().downcast::<Error>().unwrap();
+            assert_eq!(*error, expected, "{input:X?}");
+            assert_eq!(error.error_len(), None, "{input:X?}");
+        }
+    }
+
+    #[test]
+    fn writer_buffers_across_writes() {
+        let mut writer = Mutf8Writer::new(Vec::new());
+        assert_eq!(writer.write(b"a").unwrap(), 1);
+        assert_eq!(writer.get_ref(), b"a");
+        assert_eq!(writer.write(b"\0b").unwrap(), 2);
+        assert_eq!(writer.get_ref(), &[b'a', 0xC0, 0x80, b'b']);
+
+        // An incomplete character is taken, but held back until it is
+        // complete.
+        let smile = "\u{1F600}".as_bytes();
+        assert_eq!(writer.write(&smile[..1]).unwrap(), 1);
+        assert_eq!(writer.write(&smile[1..3]).unwrap(), 2);
+        assert_eq!(writer.get_ref().len(), 4);
+        assert_eq!(writer.write(&[smile[3], b'c']).unwrap(), 2);
+        assert_eq!(
+            writer.finish().unwrap(),
+            [b'a', 0xC0, 0x80, b'b', 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, b'c'],
+        );
+    }
+
+    #[test]
+    fn writer_splits_characters_across_writes() {
+        let s = "a\0\u{E9}\u{6F22}\u{1F600}z";
+        let expected = encode(s).into_owned();
+        for first in 0..=s.len() {
+            for second in first..=s.len() {
+                let mut writer = Mutf8Writer::new(Vec::new());
+                writer.write_all(&s.as_bytes()[..first]).unwrap();
+                writer.write_all(&s.as_bytes()[first..second]).unwrap();
+                writer.write_all(&s.as_bytes()[second..]).unwrap();
+                assert_eq!(writer.finish().unwrap(), expected, "{first} {second}");
+            }
+        }
+
+        let mut writer = Mutf8Writer::new(Vec::new());
+        for byte in s.bytes() {
+            assert_eq!(writer.write(&[byte]).unwrap(), 1);
+        }
+        assert_eq!(writer.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn writer_rejects_invalid_utf8() {
+        // The valid prefix is written, and the error is returned next time.
+        let mut writer = Mutf8Writer::new(Vec::new());
+        assert_eq!(writer.write(&[b'a', 0xFF, b'b']).unwrap(), 1);
+        let error = writer.write(&[0xFF, b'b']).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.get_ref().unwrap().is::<std::str::Utf8Error>());
+
+        // A character that turns out to be invalid once it is completed.
+        let mut writer = Mutf8Writer::new(Vec::new());
+        assert_eq!(writer.write(&[0xE6]).unwrap(), 1);
+        let error = writer.write(b"a").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(writer.get_ref(), b"");
+    }
+
+    #[test]
+    fn writer_finish_with_pending_bytes() {
+        let smile = "\u{1F600}".as_bytes();
+        for len in 1..smile.len() {
+            let mut writer = Mutf8Writer::new(Vec::new());
+            writer.write_all(b"a").unwrap();
+            writer.write_all(&smile[..len]).unwrap();
+            assert_eq!(writer.get_ref(), b"a");
+            let error = writer.finish().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{len}");
+            let error = error.into_inner().unwrap();
+            let error = error.downcast::<std::str::Utf8Error>().unwrap();
+            assert_eq!((error.valid_up_to(), error.error_len()), (0, None));
+        }
+    }
+
+    #[test]
+    fn write_utf_too_long() {
+        let mut data = vec![0xAA];
+        let error = data.write_utf(&"\0".repeat(32_768)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = error.into_inner().unwrap().downcast::<TooLong>().unwrap();
+        assert_eq!(error.encoded_len(), 65_536);
+        assert_eq!(data, [0xAA]);
+
+        let mut data = Vec::new();
+        data.write_utf(&"\0".repeat(32_767)).unwrap();
+        assert_eq!(data.len(), 2 + 65_534);
+        assert_eq!(data[..4], [0xFF, 0xFE, 0xC0, 0x80]);
+        assert_eq!((&data[..]).read_utf().unwrap(), "\0".repeat(32_767));
+    }
+
+    #[test]
+    fn read_utf_unexpected_eof() {
+        for data in [&[][..], &[0x00], &[0x00, 0x02, b'a'], &[0xFF, 0xFF, b'a']] {
+            let error = (&data[..]).read_utf().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "{data:X?}");
+        }
+        // The payload is decoded only once all of it has been read.
+        let error = Trickle(&[0x00, 0x03, b'a', 0xC0]).read_utf().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // The position of invalid data counts the length prefix.
+        let error = (&[0x00, 0x02, b'a', 0xC0, b'b'][..])
+            .read_utf()
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = error.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!((error.valid_up_to(), error.error_len()), (3, None));
+    }
+}
@@ -0,0 +1,1393 @@
+//! Walking a Java Object Serialization stream.
+//!
+//! [`parse`] reads a stream written by `java.io.ObjectOutputStream` into a
+//! tree of [`Content`], without needing to know any of the classes in it.
+//! Class and field names are written the way
+//! [`write_utf`](crate::write_utf) writes them, and strings are written as
+//! `TC_STRING` with a 16-bit length or as `TC_LONGSTRING` with a 64-bit length
+//! once their MUTF-8 representation is longer than 65535 bytes. Either way,
+//! they are decoded by the same rules as [`decode`].
+//!
+//! Every object in the stream is assigned a handle, by which later parts of
+//! the stream can refer back to it. Such a back reference is kept as an
+//! [`Object::Reference`], except where a field descriptor refers back to the
+//! string naming the class of the field, which is resolved to the string.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use mutf8::java_serialization::{self, Content, Object};
+//!
+//! // The result of writing "a\0" twice with `ObjectOutputStream.writeObject`.
+//! let data = &[
+//!     0xAC, 0xED, 0x00, 0x05, // magic and version
+//!     0x74, 0x00, 0x03, b'a', 0xC0, 0x80, // TC_STRING "a\0"
+//!     0x71, 0x00, 0x7E, 0x00, 0x00, // TC_REFERENCE to the string
+//! ];
+//!
+//! let stream = java_serialization::parse(data).unwrap();
+//! assert_eq!(stream.version, 5);
+//! assert_eq!(
+//!     stream.contents,
+//!     [
+//!         Content::Object(Object::String { handle: 0x7E_0000, value: "a\0".to_string() }),
+//!         Content::Object(Object::Reference(0x7E_0000)),
+//!     ],
+//! );
+//! ```
+
+use crate::{decode, Error};
+use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
+use core::fmt;
+
+/// The magic number every stream starts with.
+const STREAM_MAGIC: u16 = 0xACED;
+
+/// The only version of the stream format in use.
+const STREAM_VERSION: u16 = 5;
+
+/// The handle assigned to the first object of a stream.
+const BASE_WIRE_HANDLE: u32 = 0x7E_0000;
+
+/// How deeply objects may be nested.
+const MAX_DEPTH: usize = 256;
+
+const TC_NULL: u8 = 0x70;
+const TC_REFERENCE: u8 = 0x71;
+const TC_CLASSDESC: u8 = 0x72;
+const TC_OBJECT: u8 = 0x73;
+const TC_STRING: u8 = 0x74;
+const TC_ARRAY: u8 = 0x75;
+const TC_CLASS: u8 = 0x76;
+const TC_BLOCKDATA: u8 = 0x77;
+const TC_ENDBLOCKDATA: u8 = 0x78;
+const TC_RESET: u8 = 0x79;
+const TC_BLOCKDATALONG: u8 = 0x7A;
+const TC_EXCEPTION: u8 = 0x7B;
+const TC_LONGSTRING: u8 = 0x7C;
+const TC_PROXYCLASSDESC: u8 = 0x7D;
+const TC_ENUM: u8 = 0x7E;
+
+/// The class has a `writeObject` method that may write extra data.
+pub const SC_WRITE_METHOD: u8 = 0x01;
+/// The class is `Serializable`.
+pub const SC_SERIALIZABLE: u8 = 0x02;
+/// The class is `Externalizable`.
+pub const SC_EXTERNALIZABLE: u8 = 0x04;
+/// The `Externalizable` data of the class is written in block data.
+pub const SC_BLOCK_DATA: u8 = 0x08;
+/// The class is an enum.
+pub const SC_ENUM: u8 = 0x10;
+
+/// A parsed Java Object Serialization stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stream<'a> {
+    /// The version of the stream format, which is always 5.
+    pub version: u16,
+    /// The contents of the stream, in the order they were written.
+    pub contents: Vec<Content<'a>>,
+}
+
+/// A piece of content of a [`Stream`], or of the annotation of a class or an
+/// object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Content<'a> {
+    /// An object, written by `writeObject`.
+    Object(Object<'a>),
+    /// Primitive data, written by the methods of `DataOutput`.
+    BlockData(&'a [u8]),
+    /// The exception that aborted writing the stream. Whatever was being
+    /// written when it was thrown is left incomplete, and is not part of the
+    /// contents.
+    Exception(Object<'a>),
+}
+
+/// An object of a [`Stream`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Object<'a> {
+    /// A null reference.
+    Null,
+    /// A reference to an object written earlier, by its handle.
+    Reference(u32),
+    /// A string.
+    String {
+        /// The handle of the string.
+        handle: u32,
+        /// The decoded string.
+        value: String,
+    },
+    /// A class descriptor.
+    ClassDesc(Box<ClassDesc<'a>>),
+    /// An instance of a class.
+    Instance {
+        /// The class descriptor of the class, which is either a
+        /// [`Object::ClassDesc`] or a [`Object::Reference`] to one.
+        class_desc: Box<Object<'a>>,
+        /// The handle of the instance.
+        handle: u32,
+        /// The data of the instance, one entry for each `Serializable` class
+        /// in its hierarchy, from the topmost superclass down. An
+        /// `Externalizable` instance has a single entry instead.
+        class_data: Vec<ClassData<'a>>,
+    },
+    /// An array.
+    Array {
+        /// The class descriptor of the array type, which is either a
+        /// [`Object::ClassDesc`] or a [`Object::Reference`] to one.
+        class_desc: Box<Object<'a>>,
+        /// The handle of the array.
+        handle: u32,
+        /// The elements of the array.
+        values: Vec<Value<'a>>,
+    },
+    /// A `java.lang.Class`.
+    Class {
+        /// The class descriptor of the class, which is either a
+        /// [`Object::ClassDesc`] or a [`Object::Reference`] to one.
+        class_desc: Box<Object<'a>>,
+        /// The handle of the class.
+        handle: u32,
+    },
+    /// An enum constant.
+    Enum {
+        /// The class descriptor of the enum, which is either a
+        /// [`Object::ClassDesc`] or a [`Object::Reference`] to one.
+        class_desc: Box<Object<'a>>,
+        /// The handle of the enum constant.
+        handle: u32,
+        /// The name of the enum constant, which is either a
+        /// [`Object::String`] or a [`Object::Reference`] to one.
+        name: Box<Object<'a>>,
+    },
+}
+
+/// A class descriptor, describing how instances of a class are serialized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClassDesc<'a> {
+    /// The class descriptor of an ordinary class.
+    Class {
+        /// The binary name of the class.
+        name: String,
+        /// The `serialVersionUID` of the class.
+        serial_version_uid: i64,
+        /// The handle of the class descriptor.
+        handle: u32,
+        /// The `SC_*` flags of the class.
+        flags: u8,
+        /// The serializable fields of the class, in the order their values
+        /// are written.
+        fields: Vec<FieldDesc>,
+        /// The annotation written by `annotateClass`.
+        annotation: Vec<Content<'a>>,
+        /// The class descriptor of the superclass, which is either a
+        /// [`Object::ClassDesc`], a [`Object::Reference`] to one, or
+        /// [`Object::Null`].
+        super_class_desc: Object<'a>,
+    },
+    /// The class descriptor of a dynamic proxy class.
+    Proxy {
+        /// The handle of the class descriptor.
+        handle: u32,
+        /// The names of the interfaces the proxy class implements.
+        interfaces: Vec<String>,
+        /// The annotation written by `annotateProxyClass`.
+        annotation: Vec<Content<'a>>,
+        /// The class descriptor of the superclass, which is either a
+        /// [`Object::ClassDesc`], a [`Object::Reference`] to one, or
+        /// [`Object::Null`].
+        super_class_desc: Object<'a>,
+    },
+}
+
+impl ClassDesc<'_> {
+    /// Returns the handle of the class descriptor.
+    #[must_use]
+    #[inline]
+    pub const fn handle(&self) -> u32 {
+        match *self {
+            ClassDesc::Class { handle, .. } | ClassDesc::Proxy { handle, .. } => handle,
+        }
+    }
+}
+
+/// A serializable field of a class.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldDesc {
+    /// The type code of the field, such as `b'I'` for an `int` or `b'L'` for
+    /// an object.
+    pub type_code: u8,
+    /// The name of the field.
+    pub name: String,
+    /// The type of an object field in field descriptor form, such as
+    /// `Ljava/lang/String;`, or `None` for a primitive field.
+    pub class_name: Option<String>,
+}
+
+/// The data written for one class in the hierarchy of an instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassData<'a> {
+    /// The values of the serializable fields of the class.
+    pub values: Vec<Value<'a>>,
+    /// The annotation written by `writeObject` or `writeExternal`, if any.
+    pub annotation: Vec<Content<'a>>,
+}
+
+/// The value of a field or an array element.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// A `byte`.
+    Byte(i8),
+    /// A `char`, which is a UTF-16 code unit.
+    Char(u16),
+    /// A `double`.
+    Double(f64),
+    /// A `float`.
+    Float(f32),
+    /// An `int`.
+    Int(i32),
+    /// A `long`.
+    Long(i64),
+    /// A `short`.
+    Short(i16),
+    /// A `boolean`.
+    Boolean(bool),
+    /// An object, including an array.
+    Object(Object<'a>),
+}
+
+/// Parses a Java Object Serialization stream.
+///
+/// The whole slice of bytes is taken to be the stream.
+///
+/// # Errors
+///
+/// Returns [`SerializationError`] if the stream is malformed, or if it holds
+/// an `Externalizable` object written with the old protocol, which cannot be
+/// walked without knowing the class.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::java_serialization::{self, SerializationError};
+///
+/// let stream = java_serialization::parse(&[0xAC, 0xED, 0x00, 0x05]).unwrap();
+/// assert!(stream.contents.is_empty());
+///
+/// let error = java_serialization::parse(&[0xCA, 0xFE, 0xBA, 0xBE]).unwrap_err();
+/// assert_eq!(error, SerializationError::InvalidMagic(0xCAFE));
+/// ```
+pub fn parse(bytes: &[u8]) -> Result<Stream<'_>, SerializationError> {
+    let mut parser = Parser {
+        bytes,
+        pos: 0,
+        handles: Vec::new(),
+        depth: 0,
+        exception: None,
+    };
+    let magic = u16::from_be_bytes(parser.array()?);
+    if magic != STREAM_MAGIC {
+        return Err(SerializationError::InvalidMagic(magic));
+    }
+    let version = u16::from_be_bytes(parser.array()?);
+    if version != STREAM_VERSION {
+        return Err(SerializationError::UnsupportedVersion(version));
+    }
+
+    let mut contents = Vec::new();
+    while parser.pos < bytes.len() {
+        if parser.peek()? == TC_RESET {
+            parser.pos += 1;
+            parser.handles.clear();
+            continue;
+        }
+        match parser.content() {
+            Ok(content) => contents.push(content),
+            Err(Stop::Aborted) => {
+                contents.extend(parser.exception.take().map(Content::Exception));
+            }
+            Err(Stop::Error(error)) => return Err(error),
+        }
+    }
+
+    Ok(Stream { version, contents })
+}
+
+/// Why the parser stopped short of the end of whatever it was reading.
+enum Stop {
+    /// The stream is malformed.
+    Error(SerializationError),
+    /// The writer was aborted by an exception, which is held by the parser.
+    Aborted,
+}
+
+impl From<SerializationError> for Stop {
+    #[inline]
+    fn from(error: SerializationError) -> Self {
+        Stop::Error(error)
+    }
+}
+
+/// What a handle refers to, as far as the parser needs to know.
+enum Entry {
+    /// A class descriptor.
+    ClassDesc(Rc<Layout>),
+    /// A string.
+    String(Rc<str>),
+    /// Anything else, including a class descriptor that is still being read.
+    Other,
+}
+
+/// How instances of a class are laid out in the stream.
+struct Layout {
+    name: Rc<str>,
+    flags: u8,
+    type_codes: Vec<u8>,
+    superclass: Option<Rc<Layout>>,
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    handles: Vec<Entry>,
+    depth: usize,
+    exception: Option<Object<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SerializationError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(SerializationError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SerializationError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn peek(&self) -> Result<u8, SerializationError> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or(SerializationError::UnexpectedEnd)
+    }
+
+    fn len(&mut self) -> Result<usize, SerializationError> {
+        let len = i32::from_be_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| SerializationError::NegativeLength(len))
+    }
+
+    fn utf(&mut self, len: usize) -> Result<String, SerializationError> {
+        let start = self.pos;
+        let bytes = self.take(len)?;
+        match decode(bytes) {
+            Ok(s) => Ok(s.into_owned()),
+            Err(error) => Err(SerializationError::Mutf8(error.offset(start))),
+        }
+    }
+
+    fn short_utf(&mut self) -> Result<String, SerializationError> {
+        let len = u16::from_be_bytes(self.array()?);
+        self.utf(usize::from(len))
+    }
+
+    fn new_handle(&mut self, entry: Entry) -> Result<u32, SerializationError> {
+        let handle = u32::try_from(self.handles.len())
+            .ok()
+            .and_then(|index| BASE_WIRE_HANDLE.checked_add(index))
+            .ok_or(SerializationError::TooManyHandles)?;
+        self.handles.push(entry);
+        Ok(handle)
+    }
+
+    fn entry(&self, handle: u32) -> Result<&Entry, SerializationError> {
+        handle
+            .checked_sub(BASE_WIRE_HANDLE)
+            .and_then(|index| self.handles.get(index as usize))
+            .ok_or(SerializationError::InvalidHandle(handle))
+    }
+
+    fn content(&mut self) -> Result<Content<'a>, Stop> {
+        match self.peek()? {
+            TC_BLOCKDATA => {
+                self.pos += 1;
+                let [len] = self.array()?;
+                Ok(Content::BlockData(self.take(usize::from(len))?))
+            }
+            TC_BLOCKDATALONG => {
+                self.pos += 1;
+                let len = self.len()?;
+                Ok(Content::BlockData(self.take(len)?))
+            }
+            _ => Ok(Content::Object(self.object()?)),
+        }
+    }
+
+    /// Reads contents up to and including the `TC_ENDBLOCKDATA` that ends
+    /// them.
+    fn annotation(&mut self) -> Result<Vec<Content<'a>>, Stop> {
+        let mut contents = Vec::new();
+        while self.peek()? != TC_ENDBLOCKDATA {
+            contents.push(self.content()?);
+        }
+        self.pos += 1;
+        Ok(contents)
+    }
+
+    fn object(&mut self) -> Result<Object<'a>, Stop> {
+        if self.depth == MAX_DEPTH {
+            return Err(SerializationError::TooDeep.into());
+        }
+        self.depth += 1;
+        let object = self.object_inner();
+        self.depth -= 1;
+        object
+    }
+
+    fn object_inner(&mut self) -> Result<Object<'a>, Stop> {
+        let [tc] = self.array()?;
+        match tc {
+            TC_NULL => Ok(Object::Null),
+            TC_REFERENCE => {
+                let handle = u32::from_be_bytes(self.array()?);
+                self.entry(handle)?;
+                Ok(Object::Reference(handle))
+            }
+            TC_CLASSDESC => Ok(Object::ClassDesc(Box::new(self.new_class_desc()?))),
+            TC_PROXYCLASSDESC => Ok(Object::ClassDesc(Box::new(self.new_proxy_class_desc()?))),
+            TC_STRING => {
+                let len = u16::from_be_bytes(self.array()?);
+                self.new_string(usize::from(len))
+            }
+            TC_LONGSTRING => {
+                let len = u64::from_be_bytes(self.array()?);
+                // No slice can be longer than `isize::MAX` bytes.
+                let len = isize::try_from(len)
+                    .ok()
+                    .and_then(|len| usize::try_from(len).ok())
+                    .ok_or(SerializationError::LengthOverflow(len))?;
+                self.new_string(len)
+            }
+            TC_OBJECT => self.new_object(),
+            TC_ARRAY => self.new_array(),
+            TC_CLASS => {
+                let (class_desc, _) = self.class_desc()?;
+                let handle = self.new_handle(Entry::Other)?;
+                Ok(Object::Class {
+                    class_desc: Box::new(class_desc),
+                    handle,
+                })
+            }
+            TC_ENUM => self.new_enum(),
+            TC_EXCEPTION => {
+                self.handles.clear();
+                let exception = self.object()?;
+                self.handles.clear();
+                self.exception = Some(exception);
+                Err(Stop::Aborted)
+            }
+            tc => Err(SerializationError::UnexpectedTypeCode(tc).into()),
+        }
+    }
+
+    fn new_string(&mut self, len: usize) -> Result<Object<'a>, Stop> {
+        let value = self.utf(len)?;
+        let handle = self.new_handle(Entry::String(Rc::from(value.as_str())))?;
+        Ok(Object::String { handle, value })
+    }
+
+    fn new_object(&mut self) -> Result<Object<'a>, Stop> {
+        let (class_desc, layout) = self.class_desc()?;
+        let handle = self.new_handle(Entry::Other)?;
+        let class_data = self.class_data(&layout)?;
+        Ok(Object::Instance {
+            class_desc: Box::new(class_desc),
+            handle,
+            class_data,
+        })
+    }
+
+    fn new_array(&mut self) -> Result<Object<'a>, Stop> {
+        let (class_desc, layout) = self.class_desc()?;
+        let handle = self.new_handle(Entry::Other)?;
+        let type_code = match layout.name.as_bytes() {
+            [b'[', type_code, ..] => *type_code,
+            name => {
+                let type_code = name.first().copied().unwrap_or(0);
+                return Err(SerializationError::InvalidFieldType(type_code).into());
+            }
+        };
+        let len = self.len()?;
+        let mut values = Vec::with_capacity(len.min(self.bytes.len() - self.pos));
+        for _ in 0..len {
+            values.push(self.value(type_code)?);
+        }
+        Ok(Object::Array {
+            class_desc: Box::new(class_desc),
+            handle,
+            values,
+        })
+    }
+
+    fn new_enum(&mut self) -> Result<Object<'a>, Stop> {
+        let (class_desc, _) = self.class_desc()?;
+        let handle = self.new_handle(Entry::Other)?;
+        let name = self.string()?;
+        Ok(Object::Enum {
+            class_desc: Box::new(class_desc),
+            handle,
+            name: Box::new(name),
+        })
+    }
+
+    /// Reads a class descriptor where one is expected, returning it along with
+    /// the layout of the class.
+    fn class_desc(&mut self) -> Result<(Object<'a>, Rc<Layout>), Stop> {
+        match self.peek()? {
+            TC_REFERENCE => {
+                self.pos += 1;
+                let handle = u32::from_be_bytes(self.array()?);
+                let layout = self.class_desc_layout(handle)?;
+                Ok((Object::Reference(handle), layout))
+            }
+            TC_CLASSDESC | TC_PROXYCLASSDESC => {
+                let class_desc = self.object()?;
+                let Object::ClassDesc(new_class_desc) = &class_desc else {
+                    unreachable!("a class descriptor tag was read as something else");
+                };
+                let layout = self.class_desc_layout(new_class_desc.handle())?;
+                Ok((class_desc, layout))
+            }
+            tc => Err(SerializationError::UnexpectedTypeCode(tc).into()),
+        }
+    }
+
+    /// Reads the class descriptor of a superclass, which may be a null
+    /// reference if there is none.
+    fn super_class_desc(&mut self) -> Result<(Object<'a>, Option<Rc<Layout>>), Stop> {
+        if self.peek()? == TC_NULL {
+            self.pos += 1;
+            return Ok((Object::Null, None));
+        }
+        let (class_desc, layout) = self.class_desc()?;
+        Ok((class_desc, Some(layout)))
+    }
+
+    fn class_desc_layout(&self, handle: u32) -> Result<Rc<Layout>, SerializationError> {
+        match self.entry(handle)? {
+            Entry::ClassDesc(layout) => Ok(Rc::clone(layout)),
+            _ => Err(SerializationError::InvalidHandle(handle)),
+        }
+    }
+
+    /// Reads a string where one is expected, which may also be a reference to
+    /// a string written earlier.
+    fn string(&mut self) -> Result<Object<'a>, Stop> {
+        match self.peek()? {
+            TC_STRING | TC_LONGSTRING => self.object(),
+            TC_REFERENCE => {
+                self.pos += 1;
+                let handle = u32::from_be_bytes(self.array()?);
+                match self.entry(handle)? {
+                    Entry::String(_) => Ok(Object::Reference(handle)),
+                    _ => Err(SerializationError::InvalidHandle(handle).into()),
+                }
+            }
+            tc => Err(SerializationError::UnexpectedTypeCode(tc).into()),
+        }
+    }
+
+    fn new_class_desc(&mut self) -> Result<ClassDesc<'a>, Stop> {
+        let name = self.short_utf()?;
+        let serial_version_uid = i64::from_be_bytes(self.array()?);
+        let handle = self.new_handle(Entry::Other)?;
+        let [flags] = self.array()?;
+
+        let count = u16::from_be_bytes(self.array()?);
+        let mut fields = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let [type_code] = self.array()?;
+            let name = self.short_utf()?;
+            let class_name = match type_code {
+                b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => None,
+                b'L' | b'[' => self.field_class_name()?,
+                _ => return Err(SerializationError::InvalidFieldType(type_code).into()),
+            };
+            fields.push(FieldDesc {
+                type_code,
+                name,
+                class_name,
+            });
+        }
+
+        let annotation = self.annotation()?;
+        let (super_class_desc, superclass) = self.super_class_desc()?;
+        let layout = Rc::new(Layout {
+            name: Rc::from(name.as_str()),
+            flags,
+            type_codes: fields.iter().map(|field| field.type_code).collect(),
+            superclass,
+        });
+        self.handles[(handle - BASE_WIRE_HANDLE) as usize] = Entry::ClassDesc(layout);
+
+        let class_desc = ClassDesc::Class {
+            name,
+            serial_version_uid,
+            handle,
+            flags,
+            fields,
+            annotation,
+            super_class_desc,
+        };
+        Ok(class_desc)
+    }
+
+    fn new_proxy_class_desc(&mut self) -> Result<ClassDesc<'a>, Stop> {
+        let handle = self.new_handle(Entry::Other)?;
+        let count = self.len()?;
+        let mut interfaces = Vec::with_capacity(count.min(self.bytes.len() - self.pos));
+        for _ in 0..count {
+            interfaces.push(self.short_utf()?);
+        }
+
+        let annotation = self.annotation()?;
+        let (super_class_desc, superclass) = self.super_class_desc()?;
+        let layout = Rc::new(Layout {
+            name: Rc::from(""),
+            flags: SC_SERIALIZABLE,
+            type_codes: Vec::new(),
+            superclass,
+        });
+        self.handles[(handle - BASE_WIRE_HANDLE) as usize] = Entry::ClassDesc(layout);
+
+        let class_desc = ClassDesc::Proxy {
+            handle,
+            interfaces,
+            annotation,
+            super_class_desc,
+        };
+        Ok(class_desc)
+    }
+
+    /// Reads the string naming the class of an object field, resolving a
+    /// reference to a string written earlier.
+    fn field_class_name(&mut self) -> Result<Option<String>, Stop> {
+        if self.peek()? == TC_NULL {
+            self.pos += 1;
+            return Ok(None);
+        }
+        match self.string()? {
+            Object::String { value, .. } => Ok(Some(value)),
+            Object::Reference(handle) => match self.entry(handle)? {
+                Entry::String(value) => Ok(Some(String::from(&**value))),
+                _ => unreachable!("a string reference was not to a string"),
+            },
+            _ => unreachable!("a string was read as something else"),
+        }
+    }
+
+    fn class_data(&mut self, layout: &Rc<Layout>) -> Result<Vec<ClassData<'a>>, Stop> {
+        if layout.flags & SC_EXTERNALIZABLE != 0 {
+            if layout.flags & SC_BLOCK_DATA == 0 {
+                return Err(SerializationError::ExternalContents.into());
+            }
+            let annotation = self.annotation()?;
+            return Ok(Vec::from([ClassData {
+                values: Vec::new(),
+                annotation,
+            }]));
+        }
+
+        let mut hierarchy = Vec::new();
+        let mut next = Some(layout);
+        while let Some(layout) = next {
+            if layout.flags & SC_SERIALIZABLE != 0 {
+                hierarchy.push(layout);
+            }
+            next = layout.superclass.as_ref();
+        }
+
+        let mut class_data = Vec::with_capacity(hierarchy.len());
+        for layout in hierarchy.into_iter().rev() {
+            let mut values = Vec::with_capacity(layout.type_codes.len());
+            for &type_code in &layout.type_codes {
+                values.push(self.value(type_code)?);
+            }
+            let annotation = if layout.flags & SC_WRITE_METHOD == 0 {
+                Vec::new()
+            } else {
+                self.annotation()?
+            };
+            class_data.push(ClassData { values, annotation });
+        }
+        Ok(class_data)
+    }
+
+    fn value(&mut self, type_code: u8) -> Result<Value<'a>, Stop> {
+        Ok(match type_code {
+            b'B' => Value::Byte(i8::from_be_bytes(self.array()?)),
+            b'C' => Value::Char(u16::from_be_bytes(self.array()?)),
+            b'D' => Value::Double(f64::from_be_bytes(self.array()?)),
+            b'F' => Value::Float(f32::from_be_bytes(self.array()?)),
+            b'I' => Value::Int(i32::from_be_bytes(self.array()?)),
+            b'J' => Value::Long(i64::from_be_bytes(self.array()?)),
+            b'S' => Value::Short(i16::from_be_bytes(self.array()?)),
+            b'Z' => Value::Boolean(self.array::<1>()? != [0]),
+            b'L' | b'[' => Value::Object(self.object()?),
+            _ => return Err(SerializationError::InvalidFieldType(type_code).into()),
+        })
+    }
+}
+
+/// An error thrown when a Java Object Serialization stream cannot be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializationError {
+    /// The stream does not start with `0xACED`.
+    InvalidMagic(u16),
+    /// The version of the stream format is not 5.
+    UnsupportedVersion(u16),
+    /// The input ended in the middle of the stream.
+    UnexpectedEnd,
+    /// A type code is unknown, or is not allowed where it was found.
+    UnexpectedTypeCode(u8),
+    /// The type code of a field or of the elements of an array is unknown.
+    InvalidFieldType(u8),
+    /// A reference is to a handle that was never assigned, or to an object of
+    /// the wrong kind.
+    InvalidHandle(u32),
+    /// The length of block data or of an array is negative.
+    NegativeLength(i32),
+    /// The 64-bit length of a long string is too large to ever fit in memory.
+    LengthOverflow(u64),
+    /// An `Externalizable` object was written with the old protocol, which
+    /// cannot be walked without knowing the class.
+    ExternalContents,
+    /// Objects are nested more than 256 levels deep.
+    TooDeep,
+    /// More objects were assigned a handle than fit in the 32-bit handles of
+    /// the stream format.
+    TooManyHandles,
+    /// A string is invalid MUTF-8 data. The position given by
+    /// [`valid_up_to`](Error::valid_up_to) is relative to the start of the
+    /// stream.
+    Mutf8(Error),
+}
+
+impl fmt::Display for SerializationError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SerializationError::InvalidMagic(magic) => {
+                write!(f, "invalid stream magic number {magic:#06X}")
+            }
+            SerializationError::UnsupportedVersion(version) => {
+                write!(f, "unsupported stream version {version}")
+            }
+            SerializationError::UnexpectedEnd => f.write_str("unexpected end of stream"),
+            SerializationError::UnexpectedTypeCode(tc) => {
+                write!(f, "unexpected type code {tc:#04X}")
+            }
+            SerializationError::InvalidFieldType(type_code) => {
+                write!(f, "invalid field type code {type_code:#04X}")
+            }
+            SerializationError::InvalidHandle(handle) => {
+                write!(f, "invalid handle {handle:#X}")
+            }
+            SerializationError::NegativeLength(len) => write!(f, "negative length {len}"),
+            SerializationError::LengthOverflow(len) => {
+                write!(f, "length {len} too large to fit in memory")
+            }
+            SerializationError::ExternalContents => {
+                f.write_str("externalizable data written without block data")
+            }
+            SerializationError::TooDeep => {
+                write!(f, "objects nested more than {MAX_DEPTH} levels deep")
+            }
+            SerializationError::TooManyHandles => f.write_str("too many handles in stream"),
+            SerializationError::Mutf8(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for SerializationError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerializationError::Mutf8(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+    use alloc::vec;
+
+    // The streams are written by `tests/java_serialization/Gen.java`.
+    const POINTS: &[u8] = include_bytes!("../tests/java_serialization/points.ser");
+    const ANNOTATED: &[u8] = include_bytes!("../tests/java_serialization/annotated.ser");
+    const PROXY: &[u8] = include_bytes!("../tests/java_serialization/proxy.ser");
+    const RESET: &[u8] = include_bytes!("../tests/java_serialization/reset.ser");
+    const EXCEPTION: &[u8] = include_bytes!("../tests/java_serialization/exception.ser");
+    const MISC: &[u8] = include_bytes!("../tests/java_serialization/misc.ser");
+    const NODES_200: &[u8] = include_bytes!("../tests/java_serialization/nodes200.ser");
+    const NODES_300: &[u8] = include_bytes!("../tests/java_serialization/nodes300.ser");
+
+    const STREAMS: [&[u8]; 8] = [
+        POINTS, ANNOTATED, PROXY, RESET, EXCEPTION, MISC, NODES_200, NODES_300,
+    ];
+
+    fn string(handle: u32, value: &str) -> Object<'static> {
+        Object::String {
+            handle,
+            value: String::from(value),
+        }
+    }
+
+    fn objects(stream: Stream<'_>) -> Vec<Object<'_>> {
+        stream
+            .contents
+            .into_iter()
+            .map(|content| match content {
+                Content::Object(object) => object,
+                content => panic!("expected an object, found {content:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn class_desc_with_fields_and_superclass() {
+        let objects = objects(parse(POINTS).unwrap());
+        let [first, _] = &objects[..] else {
+            panic!("expected two objects, found {objects:?}");
+        };
+
+        let Object::Instance {
+            class_desc,
+            handle: 0x7E_0004,
+            class_data,
+        } = first
+        else {
+            panic!("expected an instance, found {first:?}");
+        };
+        let Object::ClassDesc(class_desc) = &**class_desc else {
+            panic!("expected a class descriptor, found {class_desc:?}");
+        };
+        let ClassDesc::Class {
+            name,
+            serial_version_uid: 2,
+            handle: 0x7E_0000,
+            flags: SC_SERIALIZABLE,
+            fields,
+            annotation,
+            super_class_desc,
+        } = &**class_desc
+        else {
+            panic!("expected the class descriptor of Point, found {class_desc:?}");
+        };
+        assert_eq!(name, "Gen$Point");
+        assert!(annotation.is_empty());
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|field| (field.type_code, &*field.name, field.class_name.as_deref()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (b'B', "by", None),
+                (b'C', "c", None),
+                (b'F', "f", None),
+                (b'Z', "flag", None),
+                (b'S', "sh", None),
+                (b'J', "x", None),
+                (b'D', "y", None),
+                (b'L', "label", Some("Ljava/lang/String;")),
+                (b'[', "values", Some("[I")),
+            ],
+        );
+        assert_eq!(
+            *super_class_desc,
+            Object::ClassDesc(Box::new(ClassDesc::Class {
+                name: String::from("Gen$Base"),
+                serial_version_uid: 1,
+                handle: 0x7E_0003,
+                flags: SC_SERIALIZABLE,
+                fields: vec![
+                    FieldDesc {
+                        type_code: b'I',
+                        name: String::from("id"),
+                        class_name: None,
+                    },
+                    // The class name refers back to the string written for
+                    // the `label` field of `Point`.
+                    FieldDesc {
+                        type_code: b'L',
+                        name: String::from("name"),
+                        class_name: Some(String::from("Ljava/lang/String;")),
+                    },
+                ],
+                annotation: Vec::new(),
+                super_class_desc: Object::Null,
+            })),
+        );
+
+        let int_array = Object::Array {
+            class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Class {
+                name: String::from("[I"),
+                serial_version_uid: 0x4DBA_6026_76EA_B2A5,
+                handle: 0x7E_0007,
+                flags: SC_SERIALIZABLE,
+                fields: Vec::new(),
+                annotation: Vec::new(),
+                super_class_desc: Object::Null,
+            }))),
+            handle: 0x7E_0008,
+            values: vec![Value::Int(1), Value::Int(2)],
+        };
+        assert_eq!(
+            *class_data,
+            point_data(
+                string(0x7E_0005, "b\0"),
+                string(0x7E_0006, "\u{1F600}"),
+                int_array
+            ),
+        );
+    }
+
+    /// Returns the data written for a point, the superclass first.
+    fn point_data<'a>(
+        name: Object<'a>,
+        label: Object<'a>,
+        values: Object<'a>,
+    ) -> Vec<ClassData<'a>> {
+        vec![
+            ClassData {
+                values: vec![Value::Int(7), Value::Object(name)],
+                annotation: Vec::new(),
+            },
+            ClassData {
+                values: vec![
+                    Value::Byte(-2),
+                    Value::Char(0xE9),
+                    Value::Float(1.5),
+                    Value::Boolean(true),
+                    Value::Short(300),
+                    Value::Long(-1),
+                    Value::Double(0.5),
+                    Value::Object(label),
+                    Value::Object(values),
+                ],
+                annotation: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn references_to_earlier_objects() {
+        let objects = objects(parse(POINTS).unwrap());
+        // The second instance refers back to the class descriptor and to
+        // the strings of the first, but gets its own array.
+        assert_eq!(
+            objects[1],
+            Object::Instance {
+                class_desc: Box::new(Object::Reference(0x7E_0000)),
+                handle: 0x7E_0009,
+                class_data: point_data(
+                    Object::Reference(0x7E_0005),
+                    Object::Reference(0x7E_0006),
+                    Object::Array {
+                        class_desc: Box::new(Object::Reference(0x7E_0007)),
+                        handle: 0x7E_000A,
+                        values: vec![Value::Int(1), Value::Int(2)],
+                    },
+                ),
+            },
+        );
+    }
+
+    #[test]
+    fn write_object_annotation() {
+        assert_eq!(
+            objects(parse(ANNOTATED).unwrap()),
+            [Object::Instance {
+                class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Class {
+                    name: String::from("Gen$Annotated"),
+                    serial_version_uid: 3,
+                    handle: 0x7E_0000,
+                    flags: SC_WRITE_METHOD | SC_SERIALIZABLE,
+                    fields: vec![FieldDesc {
+                        type_code: b'I',
+                        name: String::from("n"),
+                        class_name: None,
+                    }],
+                    annotation: Vec::new(),
+                    super_class_desc: Object::Null,
+                }))),
+                handle: 0x7E_0001,
+                class_data: vec![ClassData {
+                    values: vec![Value::Int(1)],
+                    annotation: vec![
+                        Content::BlockData(&[0x00, 0x00, 0x00, 0x2A]),
+                        Content::Object(string(0x7E_0002, "extra")),
+                    ],
+                }],
+            }],
+        );
+    }
+
+    #[test]
+    fn proxy_class_desc() {
+        assert_eq!(
+            objects(parse(PROXY).unwrap()),
+            [Object::Instance {
+                class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Proxy {
+                    handle: 0x7E_0000,
+                    interfaces: vec![String::from("java.lang.Runnable")],
+                    annotation: Vec::new(),
+                    super_class_desc: Object::ClassDesc(Box::new(ClassDesc::Class {
+                        name: String::from("java.lang.reflect.Proxy"),
+                        serial_version_uid: -0x1ED8_25DF_33EF_BC35,
+                        handle: 0x7E_0001,
+                        flags: SC_SERIALIZABLE,
+                        fields: vec![FieldDesc {
+                            type_code: b'L',
+                            name: String::from("h"),
+                            class_name: Some(String::from("Ljava/lang/reflect/InvocationHandler;")),
+                        }],
+                        annotation: Vec::new(),
+                        super_class_desc: Object::Null,
+                    })),
+                }))),
+                handle: 0x7E_0003,
+                class_data: vec![
+                    ClassData {
+                        values: vec![Value::Object(Object::Instance {
+                            class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Class {
+                                name: String::from("Gen$Handler"),
+                                serial_version_uid: 4,
+                                handle: 0x7E_0004,
+                                flags: SC_SERIALIZABLE,
+                                fields: Vec::new(),
+                                annotation: Vec::new(),
+                                super_class_desc: Object::Null,
+                            }))),
+                            handle: 0x7E_0005,
+                            class_data: vec![ClassData {
+                                values: Vec::new(),
+                                annotation: Vec::new(),
+                            }],
+                        })],
+                        annotation: Vec::new(),
+                    },
+                    // The proxy class itself has no fields.
+                    ClassData {
+                        values: Vec::new(),
+                        annotation: Vec::new(),
+                    },
+                ],
+            }],
+        );
+    }
+
+    #[test]
+    fn enum_array_class_and_block_data() {
+        let stream = parse(MISC).unwrap();
+        let string_class = |handle| {
+            Box::new(ClassDesc::Class {
+                name: String::from("java.lang.String"),
+                serial_version_uid: -0x5F0F_5BC7_85C4_4CBE,
+                handle,
+                flags: SC_SERIALIZABLE,
+                fields: Vec::new(),
+                annotation: Vec::new(),
+                super_class_desc: Object::Null,
+            })
+        };
+        assert_eq!(
+            stream.contents,
+            [
+                Content::Object(Object::Enum {
+                    class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Class {
+                        name: String::from("Gen$Color"),
+                        serial_version_uid: 0,
+                        handle: 0x7E_0000,
+                        flags: SC_ENUM | SC_SERIALIZABLE,
+                        fields: Vec::new(),
+                        annotation: Vec::new(),
+                        super_class_desc: Object::ClassDesc(Box::new(ClassDesc::Class {
+                            name: String::from("java.lang.Enum"),
+                            serial_version_uid: 0,
+                            handle: 0x7E_0001,
+                            flags: SC_ENUM | SC_SERIALIZABLE,
+                            fields: Vec::new(),
+                            annotation: Vec::new(),
+                            super_class_desc: Object::Null,
+                        })),
+                    }))),
+                    handle: 0x7E_0002,
+                    name: Box::new(string(0x7E_0003, "GREEN")),
+                }),
+                Content::Object(Object::Array {
+                    class_desc: Box::new(Object::ClassDesc(Box::new(ClassDesc::Class {
+                        name: String::from("[Ljava.lang.String;"),
+                        serial_version_uid: -0x522D_A918_16E2_84B9,
+                        handle: 0x7E_0004,
+                        flags: SC_SERIALIZABLE,
+                        fields: Vec::new(),
+                        annotation: Vec::new(),
+                        super_class_desc: Object::Null,
+                    }))),
+                    handle: 0x7E_0005,
+                    values: vec![
+                        Value::Object(string(0x7E_0006, "a")),
+                        Value::Object(Object::Null),
+                        Value::Object(Object::Reference(0x7E_0006)),
+                    ],
+                }),
+                Content::Object(Object::Class {
+                    class_desc: Box::new(Object::ClassDesc(string_class(0x7E_0007))),
+                    handle: 0x7E_0008,
+                }),
+                Content::BlockData(&[0x01, 0x02, 0x03, 0x04]),
+            ],
+        );
+    }
+
+    #[test]
+    fn long_string() {
+        // The result of writing a string of 40000 nulls twice and then "a".
+        let mut data = vec![0xAC, 0xED, 0x00, 0x05, TC_LONGSTRING];
+        data.extend_from_slice(&80_000_u64.to_be_bytes());
+        for _ in 0..40_000 {
+            data.extend_from_slice(&[0xC0, 0x80]);
+        }
+        data.extend_from_slice(&[TC_REFERENCE, 0x00, 0x7E, 0x00, 0x00]);
+        data.extend_from_slice(&[TC_STRING, 0x00, 0x01, b'a']);
+
+        let value: String = "\0".repeat(40_000);
+        assert_eq!(
+            objects(parse(&data).unwrap()),
+            [
+                Object::String {
+                    handle: 0x7E_0000,
+                    value,
+                },
+                Object::Reference(0x7E_0000),
+                string(0x7E_0001, "a"),
+            ],
+        );
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(
+            objects(parse(RESET).unwrap()),
+            [
+                string(0x7E_0000, "x"),
+                string(0x7E_0000, "x"),
+                string(0x7E_0001, "y"),
+            ],
+        );
+
+        // A reference cannot reach past a reset.
+        let data = [
+            0xAC,
+            0xED,
+            0x00,
+            0x05,
+            TC_STRING,
+            0x00,
+            0x01,
+            b'x',
+            TC_RESET,
+            TC_REFERENCE,
+            0x00,
+            0x7E,
+            0x00,
+            0x00,
+        ];
+        assert_eq!(
+            parse(&data),
+            Err(SerializationError::InvalidHandle(0x7E_0000))
+        );
+    }
+
+    #[test]
+    fn exception() {
+        let stream = parse(EXCEPTION).unwrap();
+        let [first, Content::Exception(exception)] = &stream.contents[..] else {
+            panic!(
+                "expected an object and an exception, found {:?}",
+                stream.contents
+            );
+        };
+        assert_eq!(*first, Content::Object(string(0x7E_0000, "before")));
+
+        // The handles are reset before the exception is written.
+        let Object::Instance {
+            class_desc,
+            class_data,
+            ..
+        } = exception
+        else {
+            panic!("expected an instance, found {exception:?}");
+        };
+        let Object::ClassDesc(class_desc) = &**class_desc else {
+            panic!("expected a class descriptor, found {class_desc:?}");
+        };
+        let ClassDesc::Class { name, handle, .. } = &**class_desc else {
+            panic!("expected an ordinary class descriptor, found {class_desc:?}");
+        };
+        assert_eq!(name, "java.io.NotSerializableException");
+        assert_eq!(*handle, 0x7E_0000);
+        // The data of `Throwable` holds the message.
+        assert_eq!(
+            class_data[0].values[1],
+            Value::Object(string(0x7E_000A, "java.lang.Object")),
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        let objects = objects(parse(NODES_200).unwrap());
+        let mut depth = 0;
+        let mut node = &objects[0];
+        while let Object::Instance { class_data, .. } = node {
+            let Value::Object(next) = &class_data[0].values[0] else {
+                panic!("expected an object, found {:?}", class_data[0].values[0]);
+            };
+            depth += 1;
+            node = next;
+        }
+        assert_eq!(*node, Object::Null);
+        assert_eq!(depth, 200);
+
+        assert_eq!(parse(NODES_300), Err(SerializationError::TooDeep));
+    }
+
+    #[test]
+    fn truncated() {
+        for stream in STREAMS {
+            for len in 0..stream.len() {
+                match parse(&stream[..len]) {
+                    Ok(_) | Err(SerializationError::UnexpectedEnd) => {}
+                    // The stream is too deep whether or not it is complete.
+                    Err(SerializationError::TooDeep) if stream == NODES_300 => {}
+                    Err(error) => panic!("unexpected error {error:?} at length {len}"),
+                }
+            }
+        }
+        // Only a cut between two pieces of content can be parsed.
+        assert_eq!(
+            objects(parse(&RESET[..13]).unwrap()),
+            [string(0x7E_0000, "x"), string(0x7E_0000, "x")],
+        );
+        assert_eq!(parse(&RESET[..12]), Err(SerializationError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn invalid_handle() {
+        let data = [0xAC, 0xED, 0x00, 0x05, TC_REFERENCE, 0x00, 0x7E, 0x00, 0x00];
+        assert_eq!(
+            parse(&data),
+            Err(SerializationError::InvalidHandle(0x7E_0000))
+        );
+
+        let data = [0xAC, 0xED, 0x00, 0x05, TC_REFERENCE, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(parse(&data), Err(SerializationError::InvalidHandle(0)));
+
+        // The handle of a string, where the class descriptor of the second
+        // point is expected.
+        let mut data = POINTS.to_vec();
+        let second = 0xDE;
+        assert_eq!(
+            data[second..second + 5],
+            [TC_OBJECT, TC_REFERENCE, 0x00, 0x7E, 0x00]
+        );
+        data[second + 5] = 0x05;
+        assert_eq!(
+            parse(&data),
+            Err(SerializationError::InvalidHandle(0x7E_0005))
+        );
+    }
+
+    #[test]
+    fn negative_length() {
+        // The length of the `values` array of the first point.
+        let mut data = POINTS.to_vec();
+        let len = 0xD2;
+        assert_eq!(data[len..len + 4], [0x00, 0x00, 0x00, 0x02]);
+        data[len..len + 4].copy_from_slice(&(-1_i32).to_be_bytes());
+        assert_eq!(parse(&data), Err(SerializationError::NegativeLength(-1)));
+
+        let data = [
+            0xAC,
+            0xED,
+            0x00,
+            0x05,
+            TC_BLOCKDATALONG,
+            0x80,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        assert_eq!(
+            parse(&data),
+            Err(SerializationError::NegativeLength(i32::MIN))
+        );
+    }
+
+    #[test]
+    fn length_overflow() {
+        let mut data = vec![0xAC, 0xED, 0x00, 0x05, TC_LONGSTRING];
+        data.extend_from_slice(&(1_u64 << 63).to_be_bytes());
+        assert_eq!(
+            parse(&data),
+            Err(SerializationError::LengthOverflow(1 << 63))
+        );
+
+        // A length that fits is only too long for the input.
+        let mut data = vec![0xAC, 0xED, 0x00, 0x05, TC_LONGSTRING];
+        data.extend_from_slice(&u64::from(u32::MAX).to_be_bytes());
+        assert_eq!(parse(&data), Err(SerializationError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn invalid_mutf8() {
+        // A null pair cut short by a raw null byte, in the second string.
+        let data = [
+            0xAC, 0xED, 0x00, 0x05, TC_STRING, 0x00, 0x01, b'a', TC_STRING, 0x00, 0x03, b'b', 0xC0,
+            0x00,
+        ];
+        let Err(SerializationError::Mutf8(error)) = parse(&data) else {
+            panic!("expected a MUTF-8 error");
+        };
+        assert_eq!(error.valid_up_to(), 12);
+        assert_eq!(error.kind(), ErrorKind::InvalidNullPair);
+
+        // A sequence cut off by the end of a field name.
+        let mut data = POINTS.to_vec();
+        let name = 0x1F;
+        assert_eq!(data[name - 2..name + 2], [0x00, 0x02, b'b', b'y']);
+        data[name + 1] = 0xE2;
+        let Err(SerializationError::Mutf8(error)) = parse(&data) else {
+            panic!("expected a MUTF-8 error");
+        };
+        assert_eq!(error.valid_up_to(), name + 1);
+        assert_eq!(error.error_len(), None);
+    }
+}
@@ -1,10 +1,7 @@
 //! A library for converting between MUTF-8 and UTF-8.
 //!
 //! MUTF-8 is the same as CESU-8 except for its handling of embedded null
-//! characters. This library builds on top of the `residua-cesu8` crate found
-//! [here][residua-cesu8].
-//!
-//! [residua-cesu8]: https://github.com/residua/cesu8
+//! characters.
 //!
 //! # Examples
 //!
@@ -30,10 +27,38 @@
 //! // 'str' is a null character which becomes a two-byte MUTF-8 representation.
 //! assert_eq!(mutf8::encode(str), Cow::<[u8]>::Owned(mutf8_data));
 //! ```
+//!
+//! Bytes that are known to be valid MUTF-8 can be carried around as an
+//! [`MStr`] or an owned [`MString`], neither of which ever needs to be
+//! validated again:
+//!
+//! ```
+//! # extern crate alloc;
+//! use alloc::borrow::Cow;
+//! use mutf8::{MStr, MString};
+//!
+//! let mstr = MStr::from_bytes(&[0xC0, 0x80]).unwrap();
+//! assert_eq!(Cow::from(mstr), Cow::<str>::Owned("\0".to_string()));
+//!
+//! let mstring = MString::from("\0");
+//! assert_eq!(&*mstring, mstr);
+//! ```
+//!
 //! # Features
 //!
-//! - `std` implements `std::error::Error` on `Error`. By default, this feature
-//!   is enabled.
+//! - `std` implements `std::error::Error` on `Error` and enables the [`io`]
+//!   module. By default, this feature is enabled.
+//! - `classfile` enables the `classfile` module, which reads and writes the
+//!   constant pool of a JVM class file.
+//! - `dex` enables the `dex` module, which reads and writes the strings of an
+//!   Android DEX file.
+//! - `java_serialization` enables the `java_serialization` module, which walks
+//!   a Java Object Serialization stream.
+//! - `nbt` enables the `nbt` module, which reads and writes the NBT format of
+//!   Minecraft: Java Edition.
+//! - `serde` implements `Serialize` and `Deserialize` on [`MStr`] and
+//!   [`MString`], and enables the `serde` module, which includes a data format
+//!   for the wire format of `java.io.DataOutput`.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(doc_cfg, feature(doc_cfg))]
@@ -41,8 +66,52 @@
 
 extern crate alloc;
 
+mod buffer;
+#[cfg(feature = "classfile")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "classfile")))]
+pub mod classfile;
+mod data;
+mod decoder;
+#[cfg(feature = "dex")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "dex")))]
+pub mod dex;
+mod dfa;
+mod encoder;
+mod error;
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub mod io;
+#[cfg(feature = "java_serialization")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "java_serialization")))]
+pub mod java_serialization;
+mod mstr;
+mod mstring;
+#[cfg(feature = "nbt")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "nbt")))]
+pub mod nbt;
+mod scan;
+#[cfg(feature = "serde")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+pub mod serde;
+mod simd;
+mod utf16;
+mod wtf8;
+
+pub use buffer::{decode_append, encode_append, encode_into, BufferTooSmall};
+pub use data::{encode_truncated, floor_char_boundary_for_len, read_utf, write_utf, TooLong};
+pub use decoder::{Decoder, DecoderResult};
+pub use dfa::{is_valid_mutf8, validate};
+pub use encoder::{Encoder, EncoderResult};
+pub use error::{Error, ErrorKind};
+pub use mstr::MStr;
+pub use mstring::MString;
+pub use utf16::{decode_utf16, decode_utf16_iter, encode_utf16, len_utf16, DecodeUtf16};
+pub use wtf8::{decode_wtf8, encode_wtf8};
+
 use alloc::{borrow::Cow, str::from_utf8, string::String, vec::Vec};
-use core::fmt;
+use error::Invalid;
+use simd::ByteSet;
+use utf16::encode_supplementary;
 
 /// Converts a slice of bytes to a string slice.
 ///
@@ -55,8 +124,12 @@ use core::fmt;
 /// that the slice of bytes, if not valid UTF-8, is valid MUTF-8. It will then
 /// decode the bytes given to it and return the newly constructed string slice.
 ///
+/// Raw null bytes are accepted as well, and so are 4-byte UTF-8 sequences, as
+/// long as supplementary characters are not also written as surrogate pairs:
+/// the first one decides, and the first written the other way is an error.
+///
 /// If the slice of bytes is found not to be valid MUTF-8 data, `decode()`
-/// returns `Err(Error)` to signify that an error has occurred.
+/// returns an [`Error`] describing the first invalid sequence.
 ///
 /// # Errors
 ///
@@ -86,44 +159,358 @@ use core::fmt;
 /// assert_eq!(mutf8::decode(mutf8_data), Ok(Cow::Owned(str.to_string())));
 /// ```
 #[inline]
-pub fn decode(bytes: &[u8]) -> Result<Cow<str>, Error> {
-    from_utf8(bytes)
-        .map(Cow::Borrowed)
-        .or_else(|_| decode_mutf8(bytes).map(Cow::Owned))
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, Error> {
+    match from_utf8(bytes) {
+        Ok(s) => Ok(Cow::Borrowed(s)),
+        Err(error) => decode_mutf8(bytes, error.valid_up_to()).map(Cow::Owned),
+    }
+}
+
+/// Converts a slice of bytes to a string slice, accepting only the canonical
+/// MUTF-8 encoding.
+///
+/// Unlike [`decode`], this function does not accept input just because it is
+/// valid UTF-8. A raw null byte or a 4-byte UTF-8 sequence can never be written
+/// by a MUTF-8 encoder, such as the JVM, so `decode_strict()` rejects both.
+///
+/// If the slice of bytes is valid UTF-8 that contains neither, it is also
+/// canonical MUTF-8, and `decode_strict()` returns it without allocating
+/// additional memory.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the input is not canonical MUTF-8 data.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+/// use mutf8::ErrorKind;
+///
+/// let str = "Hello, world!";
+/// // Since 'str' is canonical MUTF-8, 'decode_strict' does not allocate.
+/// assert_eq!(mutf8::decode_strict(str.as_bytes()), Ok(Cow::Borrowed(str)));
+///
+/// let mutf8_data = &[0xC0, 0x80];
+/// // 'mutf8_data' is the canonical encoding of a null character.
+/// assert_eq!(mutf8::decode_strict(mutf8_data), Ok(Cow::Owned("\0".to_string())));
+///
+/// // A raw null byte is accepted by 'decode', but not by 'decode_strict'.
+/// assert!(mutf8::decode(&[0x00]).is_ok());
+/// let error = mutf8::decode_strict(&[0x00]).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::NullByte);
+///
+/// // The same goes for a 4-byte UTF-8 character.
+/// assert!(mutf8::decode("\u{10401}".as_bytes()).is_ok());
+/// let error = mutf8::decode_strict("\u{10401}".as_bytes()).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::FourByteSequence);
+/// ```
+#[inline]
+pub fn decode_strict(bytes: &[u8]) -> Result<Cow<'_, str>, Error> {
+    match from_utf8(bytes) {
+        Ok(s) if is_valid(s) => Ok(Cow::Borrowed(s)),
+        _ => decode_mutf8_strict(bytes).map(Cow::Owned),
+    }
+}
+
+#[inline(never)]
+#[cold]
+fn decode_mutf8_strict(bytes: &[u8]) -> Result<String, Error> {
+    let mut decoded = String::with_capacity(bytes.len());
+    decode_mutf8_into(bytes, 0, &mut decoded, true)?;
+    Ok(decoded)
 }
 
 #[inline(never)]
 #[cold]
-fn decode_mutf8(bytes: &[u8]) -> Result<String, Error> {
-    macro_rules! err {
-        () => {{
-            return Err(Error);
-        }};
+fn decode_mutf8(bytes: &[u8], valid_up_to: usize) -> Result<String, Error> {
+    let mut decoded = String::with_capacity(bytes.len());
+    decode_mutf8_into(bytes, valid_up_to, &mut decoded, false)?;
+    Ok(decoded)
+}
+
+/// Decodes MUTF-8 bytes in a single pass, appending the result to `out`.
+///
+/// `bytes[..valid_up_to]` must be known to be valid UTF-8, as reported by
+/// [`Utf8Error::valid_up_to`](core::str::Utf8Error::valid_up_to), so that it
+/// can be copied without being validated again. It must be `0` if `strict` is
+/// `true`.
+///
+/// If `strict` is `false`, raw null bytes are accepted, and so are 4-byte UTF-8
+/// sequences as long as the input holds no surrogate pair, since such input is
+/// UTF-8 save for its null pairs. Whichever of the two comes first decides how
+/// supplementary characters are written, and the first one written the other
+/// way is the error. Otherwise, both are rejected. On error, `out` is left as
+/// it was.
+pub(crate) fn decode_mutf8_into(
+    bytes: &[u8],
+    valid_up_to: usize,
+    out: &mut String,
+    strict: bool,
+) -> Result<(), Error> {
+    debug_assert!(!strict || valid_up_to == 0);
+    let start = out.len();
+    // SAFETY: Only complete UTF-8 sequences are appended, and `out` is
+    // truncated back to where it started on error.
+    let decoded = unsafe { out.as_mut_vec() };
+    decoded.reserve(bytes.len());
+
+    let prefix = &bytes[..valid_up_to];
+    decoded.extend_from_slice(prefix);
+
+    let mut form = Supplementary {
+        four_byte: simd::find(prefix, ByteSet::FOUR_BYTE).is_some(),
+        surrogate_pair: false,
+    };
+    let result = decode_sequences(bytes, valid_up_to, decoded, strict, &mut form);
+    if result.is_err() {
+        decoded.truncate(start);
     }
+    result
+}
 
-    let mut decoded = Vec::with_capacity(bytes.len());
-    let mut iter = bytes.iter();
-
-    while let Some(&byte) = iter.next() {
-        let value = if byte == NULL_PAIR[0] {
-            match iter.next() {
-                Some(&byte) => {
-                    if byte != NULL_PAIR[1] {
-                        err!()
-                    }
-                }
-                _ => err!(),
+/// The ways supplementary characters have been written so far in input that
+/// is being decoded leniently.
+#[derive(Clone, Debug, Default)]
+struct Supplementary {
+    four_byte: bool,
+    surrogate_pair: bool,
+}
+
+impl Supplementary {
+    /// Decodes the code point at the start of `bytes` like
+    /// [`scan::next_code_point`], except that a 4-byte sequence is accepted
+    /// unless a surrogate pair came before it, and the other way around.
+    ///
+    /// `bytes` must not be empty.
+    fn next_code_point(&mut self, bytes: &[u8]) -> Result<(u32, usize), Invalid> {
+        match scan::next_code_point(bytes, false) {
+            Ok((_, 6)) if self.four_byte => Err(Invalid::new(ErrorKind::SplitSurrogatePair, 6)),
+            Ok((code_point, width)) => {
+                self.surrogate_pair |= width == 6;
+                Ok((code_point, width))
             }
-            NULL_CODE_POINT
-        } else {
-            byte
+            Err(Invalid {
+                kind: ErrorKind::FourByteSequence,
+                len: Some(4),
+            }) if !self.surrogate_pair => {
+                self.four_byte = true;
+                Ok((scan::four_byte_code_point(bytes), 4))
+            }
+            Err(invalid) => Err(invalid),
+        }
+    }
+}
+
+/// Decodes `bytes` from `index` on, appending the result to `decoded` up to the
+/// first invalid sequence, if any.
+///
+/// Every sequence other than a null pair or a surrogate pair is the same in
+/// UTF-8, so runs of them are validated and copied as a whole. No sequence
+/// grows when it is decoded, so `decoded` never needs more than `bytes.len()`
+/// additional bytes.
+fn decode_sequences(
+    bytes: &[u8],
+    mut index: usize,
+    decoded: &mut Vec<u8>,
+    strict: bool,
+    form: &mut Supplementary,
+) -> Result<(), Error> {
+    let set = ByteSet::decode(strict);
+    decoded.reserve(bytes.len() - index);
+    while index < bytes.len() {
+        // Null pairs and surrogate pairs are often only a few bytes of ASCII
+        // apart, which are cheaper to copy one at a time than as a run.
+        if matches!(bytes[index], 0x01..=0x7F | 0xC0) {
+            index = copy_short_runs(bytes, index, decoded);
+        }
+
+        let rest = &bytes[index..];
+        let Some(&lead) = rest.first() else {
+            break;
+        };
+        // Only a null pair, a surrogate or a 4-byte sequence can differ from
+        // UTF-8, and none of them can be found in the middle of a run of bytes
+        // that does not contain their lead bytes.
+        if !set.contains(lead) {
+            let run = simd::find(rest, set).unwrap_or(rest.len());
+            let (valid, result) = match from_utf8(&rest[..run]) {
+                Ok(_) => (run, Ok(())),
+                Err(error) => (
+                    error.valid_up_to(),
+                    Err(invalid_sequence(bytes, index + error.valid_up_to(), strict)),
+                ),
+            };
+            decoded.extend_from_slice(&rest[..valid]);
+            result?;
+            index += run;
+            continue;
+        }
+
+        // A surrogate pair is only accepted if no supplementary character was
+        // written as a 4-byte sequence before it.
+        if let [0xED, 0xA0..=0xAF, 0x80..=0xBF, 0xED, 0xB0..=0xBF, 0x80..=0xBF, ..] = *rest {
+            if form.four_byte {
+                let invalid = Invalid::new(ErrorKind::SplitSurrogatePair, 6);
+                return Err(Error::new(index, invalid));
+            }
+            form.surrogate_pair = true;
+            let high = 0xD000 | u16::from(rest[1] & 0x3F) << 6 | u16::from(rest[2] & 0x3F);
+            let low = 0xD000 | u16::from(rest[4] & 0x3F) << 6 | u16::from(rest[5] & 0x3F);
+            // SAFETY: A surrogate pair always makes a supplementary character.
+            let c = unsafe { char::from_u32_unchecked(scan::combine_surrogates(high, low)) };
+            let mut utf8 = [0; 4];
+            c.encode_utf8(&mut utf8);
+            // A supplementary character always takes 4 bytes in UTF-8.
+            decoded.extend_from_slice(&utf8);
+            index += 6;
+            continue;
+        }
+
+        index += match scan::next_code_point(rest, strict) {
+            Ok((0, width)) => {
+                decoded.push(NULL_CODE_POINT);
+                width
+            }
+            Ok((_, width)) => {
+                decoded.extend_from_slice(&rest[..width]);
+                width
+            }
+            Err(Invalid {
+                kind: ErrorKind::FourByteSequence,
+                len: Some(4),
+            }) if !strict && !form.surrogate_pair => {
+                form.four_byte = true;
+                decoded.extend_from_slice(&rest[..4]);
+                4
+            }
+            Err(invalid) => return Err(Error::new(index, invalid)),
         };
-        decoded.push(value);
     }
 
-    cesu8::decode(&decoded)
-        .map(Cow::into_owned)
-        .map_err(From::from)
+    Ok(())
+}
+
+/// Copies ASCII and null pairs from `bytes` to `decoded`, starting at `index`,
+/// until any other sequence is found or `SHORT_RUN` bytes of ASCII in a row
+/// have been copied. Returns the index it stopped at.
+///
+/// `decoded` must have room for at least `bytes.len() - index` more bytes.
+// Inlining this into `decode_sequences` slows down the copying of long runs.
+#[inline(never)]
+fn copy_short_runs(bytes: &[u8], mut index: usize, decoded: &mut Vec<u8>) -> usize {
+    let spare = decoded.spare_capacity_mut();
+    let mut written = 0;
+    let mut ascii = 0;
+    while ascii < SHORT_RUN {
+        match bytes[index..] {
+            [byte @ 0x01..=0x7F, ..] => {
+                spare[written].write(byte);
+                index += 1;
+                ascii += 1;
+            }
+            [0xC0, 0x80, ..] => {
+                spare[written].write(NULL_CODE_POINT);
+                index += 2;
+                ascii = 0;
+            }
+            _ => break,
+        }
+        written += 1;
+    }
+    // SAFETY: The first `written` bytes of the spare capacity were just
+    // initialized.
+    unsafe { decoded.set_len(decoded.len() + written) };
+    index
+}
+
+/// Describes the invalid sequence at `index`, which a run of bytes that are the
+/// same in UTF-8 was found to stop at.
+#[inline(never)]
+#[cold]
+fn invalid_sequence(bytes: &[u8], index: usize, strict: bool) -> Error {
+    match scan::next_code_point(&bytes[index..], strict) {
+        Err(invalid) => Error::new(index, invalid),
+        Ok(_) => unreachable!("MUTF-8 data was rejected without an invalid sequence"),
+    }
+}
+
+/// Converts a slice of bytes to a string slice, replacing invalid sequences
+/// with [`U+FFFD REPLACEMENT CHARACTER`][U+FFFD].
+///
+/// This is the MUTF-8 counterpart to [`String::from_utf8_lossy`]. Any input
+/// accepted by [`decode`] is decoded the same way, and if the slice of bytes is
+/// already valid UTF-8, it is returned without allocating additional memory.
+///
+/// Otherwise, each invalid sequence is replaced with a single U+FFFD, following
+/// the WHATWG "maximal subpart" practice also used by the standard library:
+///
+/// - A byte that cannot start a sequence, such as a stray continuation byte, is
+///   replaced on its own.
+/// - A sequence that is cut short by an unexpected byte is replaced as a whole,
+///   up to but not including the unexpected byte, which is then decoded anew.
+/// - A surrogate that is not part of a surrogate pair is replaced as a whole,
+///   that is, all 3 of its bytes.
+/// - A 4-byte UTF-8 sequence is replaced as a whole if a surrogate pair came
+///   before it, and a surrogate pair is replaced as a whole if a 4-byte
+///   sequence came before it, as [`decode`] would reject either.
+/// - A sequence that is cut short by the end of the input is replaced as a
+///   whole.
+///
+/// [U+FFFD]: char::REPLACEMENT_CHARACTER
+///
+/// # Examples
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+///
+/// let str = "Hello, world!";
+/// // Since 'str' contains valid UTF-8, 'decode_lossy' does not allocate.
+/// assert_eq!(mutf8::decode_lossy(str.as_bytes()), Cow::Borrowed(str));
+///
+/// // A stray continuation byte is replaced.
+/// assert_eq!(mutf8::decode_lossy(&[b'a', 0x80, b'b']), "a\u{FFFD}b");
+///
+/// // A lone high surrogate is replaced as a whole, while valid sequences
+/// // around it are still decoded.
+/// let mutf8_data = &[0xC0, 0x80, 0xED, 0xA0, 0x81, b'b'];
+/// assert_eq!(mutf8::decode_lossy(mutf8_data), "\0\u{FFFD}b");
+///
+/// // A truncated 3-byte sequence is replaced once, and the byte that cut it
+/// // short is decoded on its own.
+/// assert_eq!(mutf8::decode_lossy(&[0xE2, 0x82, b'a', 0xC0]), "\u{FFFD}a\u{FFFD}");
+/// ```
+#[must_use]
+#[inline]
+pub fn decode_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    match from_utf8(bytes) {
+        Ok(s) => Cow::Borrowed(s),
+        Err(_) => Cow::Owned(decode_mutf8_lossy(bytes)),
+    }
+}
+
+#[inline(never)]
+#[cold]
+fn decode_mutf8_lossy(bytes: &[u8]) -> String {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut form = Supplementary::default();
+    let mut index = 0;
+
+    // Decoding resumes right after each invalid sequence, keeping track of how
+    // supplementary characters were written before it, so that anything
+    // `decode` accepts comes out the same way here.
+    while let Err(error) = decode_sequences(bytes, index, &mut decoded, false, &mut form) {
+        decoded.extend_from_slice(REPLACEMENT_CHARACTER);
+        match error.error_len() {
+            Some(len) => index = error.valid_up_to() + len,
+            None => break,
+        }
+    }
+
+    // SAFETY: Only complete UTF-8 sequences are appended.
+    unsafe { String::from_utf8_unchecked(decoded) }
 }
 
 /// Converts a string slice to MUTF-8 bytes.
@@ -163,7 +550,7 @@ fn decode_mutf8(bytes: &[u8]) -> Result<String, Error> {
 /// ```
 #[must_use]
 #[inline]
-pub fn encode(s: &str) -> Cow<[u8]> {
+pub fn encode(s: &str) -> Cow<'_, [u8]> {
     if is_valid(s) {
         Cow::Borrowed(s.as_bytes())
     } else {
@@ -176,16 +563,40 @@ pub fn encode(s: &str) -> Cow<[u8]> {
 #[cold]
 fn encode_mutf8(s: &str) -> Vec<u8> {
     let mut encoded = Vec::with_capacity(len(s));
+    encode_mutf8_into(s, &mut encoded);
+    encoded
+}
 
-    for &byte in cesu8::encode(s).iter() {
-        if byte == NULL_CODE_POINT {
-            encoded.extend_from_slice(&NULL_PAIR);
-        } else {
-            encoded.push(byte);
+/// Encodes a string slice to MUTF-8 in a single pass, appending it to `out`.
+///
+/// Every character other than a null character or a supplementary character
+/// is the same in MUTF-8, so runs of them are copied as a whole.
+pub(crate) fn encode_mutf8_into(s: &str, out: &mut Vec<u8>) {
+    let bytes = s.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let rest = &bytes[index..];
+        let run = simd::find(rest, ByteSet::ENCODE).unwrap_or(rest.len());
+        out.extend_from_slice(&rest[..run]);
+        index += run;
+
+        match rest.get(run..) {
+            Some([NULL_CODE_POINT, ..]) => {
+                out.extend_from_slice(&NULL_PAIR);
+                index += 1;
+            }
+            Some(&[first, second, third, fourth, ..]) => {
+                let code_point = u32::from(first & 0x07) << 18
+                    | u32::from(second & 0x3F) << 12
+                    | u32::from(third & 0x3F) << 6
+                    | u32::from(fourth & 0x3F);
+                out.extend_from_slice(&encode_supplementary(code_point));
+                index += 4;
+            }
+            _ => {}
         }
     }
-
-    encoded
 }
 
 /// The pair of bytes the null code point (`0x00`) is represented by in MUTF-8.
@@ -193,14 +604,33 @@ const NULL_PAIR: [u8; 2] = [0xC0, 0x80];
 
 /// Given a string slice, this function returns how many bytes in MUTF-8 are
 /// required to encode the string slice.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// assert_eq!(mutf8::len("Hello, world!"), 13);
+///
+/// // A null character takes up 2 bytes instead of 1, and a supplementary
+/// // character takes up 6 bytes instead of 4.
+/// assert_eq!(mutf8::len("\0\u{10401}"), 8);
+/// ```
 #[must_use]
+#[inline]
 pub fn len(s: &str) -> usize {
-    let mut len = cesu8::len(s);
-    s.as_bytes().iter().for_each(|&b| {
-        if b == NULL_CODE_POINT {
-            len += 1;
-        }
-    });
+    let bytes = s.as_bytes();
+    let mut len = bytes.len();
+    let mut index = 0;
+    while let Some(found) = simd::find(&bytes[index..], ByteSet::ENCODE) {
+        index += found;
+        len += if bytes[index] == NULL_CODE_POINT {
+            1
+        } else {
+            2
+        };
+        index += 1;
+    }
     len
 }
 
@@ -229,33 +659,215 @@ pub fn len(s: &str) -> usize {
 #[must_use]
 #[inline]
 pub fn is_valid(s: &str) -> bool {
-    !s.contains(NULL_CHAR) && cesu8::is_valid(s)
+    simd::find(s.as_bytes(), ByteSet::ENCODE).is_none()
 }
 
 const NULL_CODE_POINT: u8 = 0x00;
-const NULL_CHAR: char = '\0';
 
-/// An error thrown by [`decode`] when the input is invalid MUTF-8 data.
-///
-/// This type does not support transmission of an error other than that an error
-/// occurred.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Error;
+/// How many bytes of ASCII the decoder copies one at a time before it looks
+/// for the end of the run instead.
+const SHORT_RUN: usize = 16;
+
+const REPLACEMENT_CHARACTER: &[u8] = "\u{FFFD}".as_bytes();
+
+#[cfg(test)]
+mod tests {
+    use crate::{decode, decode_lossy, decode_strict, decode_wtf8, Error, ErrorKind};
+    use alloc::{borrow::Cow, vec, vec::Vec};
 
-impl fmt::Display for Error {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("invalid MUTF-8 data")
+    type Decode = fn(&[u8]) -> Result<Cow<'_, str>, Error>;
+
+    /// A small generator for inputs made up of bytes that matter to MUTF-8.
+    pub(crate) fn inputs(count: usize, mut visit: impl FnMut(&[u8])) {
+        const POOL: &[u8] = &[
+            0x00, 0x41, 0x80, 0x81, 0x90, 0xA0, 0xB0, 0xBF, 0xC0, 0xC1, 0xC3, 0xE0, 0xE2, 0xED,
+            0xF0, 0xF4, 0x9F, 0x98, 0xFF,
+        ];
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut bytes = Vec::new();
+        for _ in 0..count {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            bytes.clear();
+            let mut state = seed;
+            for _ in 0..seed % 12 {
+                bytes.push(POOL[usize::try_from(state % POOL.len() as u64).unwrap()]);
+                state /= POOL.len() as u64;
+                if state == 0 {
+                    state = seed.rotate_left(17);
+                }
+            }
+            visit(&bytes);
+        }
     }
-}
 
-impl From<cesu8::Error> for Error {
-    #[inline]
-    fn from(_: cesu8::Error) -> Self {
-        Error
+    #[test]
+    fn decode_error_after_four_byte_sequence() {
+        let smile = [0xF0, 0x9F, 0x98, 0x80];
+        let cases: &[(&[u8], usize, Option<usize>, ErrorKind)] = &[
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0x80],
+                4,
+                Some(1),
+                ErrorKind::InvalidByte,
+            ),
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0xC0, 0x80, 0x80],
+                6,
+                Some(1),
+                ErrorKind::InvalidByte,
+            ),
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0xC0],
+                4,
+                None,
+                ErrorKind::UnexpectedEnd,
+            ),
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+                4,
+                Some(6),
+                ErrorKind::SplitSurrogatePair,
+            ),
+            (
+                &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xF0, 0x9F, 0x98, 0x80],
+                6,
+                Some(4),
+                ErrorKind::FourByteSequence,
+            ),
+        ];
+        for &(bytes, valid_up_to, error_len, kind) in cases {
+            let error = decode(bytes).unwrap_err();
+            assert_eq!(error.valid_up_to(), valid_up_to, "{bytes:02X?}");
+            assert_eq!(error.error_len(), error_len, "{bytes:02X?}");
+            assert_eq!(error.kind(), kind, "{bytes:02X?}");
+            assert!(decode(&bytes[..valid_up_to]).is_ok(), "{bytes:02X?}");
+        }
+        assert_eq!(decode(&smile).unwrap(), "\u{1F600}");
     }
-}
 
-#[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
-impl std::error::Error for Error {}
+    #[test]
+    fn decode_wtf8_matches_decode() {
+        let bytes = [0xF0, 0x9F, 0x98, 0x80, 0xC0, 0x80];
+        assert_eq!(
+            decode_wtf8(&bytes),
+            Ok(Cow::Owned(vec![0xF0, 0x9F, 0x98, 0x80, 0x00])),
+        );
+
+        inputs(200_000, |bytes| match decode(bytes) {
+            Ok(s) => assert_eq!(
+                decode_wtf8(bytes).as_deref(),
+                Ok(s.as_bytes()),
+                "{bytes:02X?}"
+            ),
+            // Only lone surrogates, which may also be cut off by the end of the
+            // input, are treated differently.
+            Err(error) if error.kind() == ErrorKind::UnpairedSurrogate => {}
+            Err(error) if error.error_len().is_none() => {}
+            Err(error) => assert_eq!(decode_wtf8(bytes), Err(error), "{bytes:02X?}"),
+        });
+    }
+
+    #[test]
+    fn overlong_null_pair() {
+        for (bytes, kind) in [
+            (&[b'a', 0xC0, 0x81][..], ErrorKind::Overlong),
+            (&[b'a', 0xC0, 0xBF], ErrorKind::Overlong),
+            (&[b'a', 0xC0, b'b'], ErrorKind::InvalidNullPair),
+            (&[b'a', 0xC0, 0xC0, 0x80], ErrorKind::InvalidNullPair),
+        ] {
+            for error in [
+                decode(bytes).unwrap_err(),
+                decode_strict(bytes).unwrap_err(),
+                crate::validate(bytes).unwrap_err(),
+            ] {
+                assert_eq!(error.valid_up_to(), 1, "{bytes:02X?}");
+                assert_eq!(error.error_len(), Some(1), "{bytes:02X?}");
+                assert_eq!(error.kind(), kind, "{bytes:02X?}");
+            }
+        }
+    }
+
+    #[test]
+    fn decode_lossy_replacements() {
+        let cases: &[(&[u8], &str)] = &[
+            // A byte that cannot start a sequence.
+            (&[b'a', 0x80, b'b'], "a\u{FFFD}b"),
+            (&[0xFF, 0xC1, b'a'], "\u{FFFD}\u{FFFD}a"),
+            // A sequence cut short by an unexpected byte.
+            (&[0xE2, 0x82, b'a'], "\u{FFFD}a"),
+            (&[0xC0, b'a'], "\u{FFFD}a"),
+            (&[0xE2, 0xC0, 0x80], "\u{FFFD}\0"),
+            // A surrogate that is not part of a surrogate pair.
+            (&[0xED, 0xA0, 0x81, b'b'], "\u{FFFD}b"),
+            (&[0xED, 0xB0, 0x81, b'b'], "\u{FFFD}b"),
+            (
+                &[0xED, 0xA0, 0x81, 0xED, 0xA0, 0x81, b'b'],
+                "\u{FFFD}\u{FFFD}b",
+            ),
+            // A 4-byte sequence after a surrogate pair, and the other way round.
+            (
+                &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xF0, 0x9F, 0x98, 0x80],
+                "\u{1F600}\u{FFFD}",
+            ),
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+                "\u{1F600}\u{FFFD}",
+            ),
+            // Valid characters around an invalid sequence are kept.
+            (&[0xF0, 0x9F, 0x98, 0x80, 0x80], "\u{1F600}\u{FFFD}"),
+            (
+                &[0xF0, 0x9F, 0x98, 0x80, 0xC0, 0x80, 0x80],
+                "\u{1F600}\0\u{FFFD}",
+            ),
+            // A sequence cut short by the end of the input.
+            (&[b'a', 0xE2, 0x82], "a\u{FFFD}"),
+            (&[b'a', 0xED, 0xA0, 0x81, 0xED], "a\u{FFFD}"),
+            (&[0xF0, 0x9F, 0x98, 0x80, 0xC0], "\u{1F600}\u{FFFD}"),
+        ];
+        for &(bytes, expected) in cases {
+            assert_eq!(decode_lossy(bytes), expected, "{bytes:02X?}");
+        }
+    }
+
+    #[test]
+    fn decode_lossy_keeps_valid_prefix() {
+        inputs(200_000, |bytes| match decode(bytes) {
+            Ok(decoded) => assert_eq!(decode_lossy(bytes), decoded, "{bytes:02X?}"),
+            Err(error) => {
+                let prefix = decode(&bytes[..error.valid_up_to()]).unwrap();
+                let lossy = decode_lossy(bytes);
+                assert!(lossy.starts_with(&*prefix), "{bytes:02X?}");
+                assert!(
+                    lossy[prefix.len()..].starts_with('\u{FFFD}'),
+                    "{bytes:02X?}"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn decode_valid_up_to_is_maximal() {
+        inputs(200_000, |bytes| {
+            for (name, decode) in [
+                ("decode", decode as Decode),
+                ("decode_strict", decode_strict as Decode),
+            ] {
+                let Err(error) = decode(bytes) else { continue };
+                let valid_up_to = error.valid_up_to();
+                assert!(decode(&bytes[..valid_up_to]).is_ok(), "{name} {bytes:02X?}");
+                for end in valid_up_to + 1..=bytes.len() {
+                    assert!(decode(&bytes[..end]).is_err(), "{name} {bytes:02X?} {end}");
+                }
+                if error.error_len().is_none() {
+                    assert_eq!(
+                        error.kind(),
+                        ErrorKind::UnexpectedEnd,
+                        "{name} {bytes:02X?}"
+                    );
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{decode, validate, Error};
+use alloc::borrow::Cow;
+use core::fmt;
+
+/// A borrowed slice of bytes that is known to be valid MUTF-8.
+///
+/// `MStr` is to MUTF-8 what [`str`] is to UTF-8: it can only be constructed
+/// from bytes that have passed validation, so code holding a `&MStr` never
+/// needs to check the data again. It is always used behind a reference, such
+/// as `&MStr`.
+///
+/// The bytes of an `MStr` are always in the canonical MUTF-8 form. They never
+/// contain a raw null byte (`0x00`) nor a 4-byte UTF-8 sequence; both are
+/// instead encoded the way the JVM encodes them.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+/// use mutf8::MStr;
+///
+/// let mstr = MStr::from_bytes(&[0xC0, 0x80]).unwrap();
+/// assert_eq!(mstr.as_bytes(), &[0xC0, 0x80]);
+/// assert_eq!(mstr.to_str(), Cow::<str>::Owned("\0".to_string()));
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct MStr {
+    bytes: [u8],
+}
+
+impl MStr {
+    /// Converts a slice of bytes to an `MStr` after checking that it is valid
+    /// MUTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the input is not canonical MUTF-8 data, in the same
+    /// cases as [`decode_strict`](crate::decode_strict). This includes input
+    /// that [`decode`] would accept only because it is valid UTF-8, such as
+    /// input containing a raw null byte or a 4-byte UTF-8 sequence.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::MStr;
+    ///
+    /// let mstr = MStr::from_bytes(b"Hello, world!");
+    /// assert!(mstr.is_ok());
+    ///
+    /// // A raw null byte is valid UTF-8, but it is not valid MUTF-8.
+    /// assert!(MStr::from_bytes(&[0x00]).is_err());
+    ///
+    /// // A 4-byte UTF-8 character must be written as a surrogate pair.
+    /// assert!(MStr::from_bytes("\u{10401}".as_bytes()).is_err());
+    /// assert!(MStr::from_bytes(&[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]).is_ok());
+    /// ```
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<&MStr, Error> {
+        validate(bytes)?;
+        // SAFETY: The bytes were validated above.
+        Ok(unsafe { MStr::from_bytes_unchecked(bytes) })
+    }
+
+    /// Converts a slice of bytes to an `MStr` without checking that it is
+    /// valid MUTF-8.
+    ///
+    /// # Safety
+    ///
+    /// The bytes passed in must be valid MUTF-8, as accepted by
+    /// [`MStr::from_bytes`].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::MStr;
+    ///
+    /// // SAFETY: 'b"Hello"' is valid MUTF-8.
+    /// let mstr = unsafe { MStr::from_bytes_unchecked(b"Hello") };
+    /// assert_eq!(mstr.as_bytes(), b"Hello");
+    /// ```
+    #[must_use]
+    #[inline]
+    pub const unsafe fn from_bytes_unchecked(bytes: &[u8]) -> &MStr {
+        // SAFETY: `MStr` is `repr(transparent)` over `[u8]`.
+        &*(core::ptr::from_ref::<[u8]>(bytes) as *const MStr)
+    }
+
+    /// Returns the MUTF-8 bytes of this `MStr`.
+    #[must_use]
+    #[inline]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Decodes this `MStr` to a string slice.
+    ///
+    /// This is functionally no different than calling [`decode`] on the bytes
+    /// of the `MStr`, except that it can never fail.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// # extern crate alloc;
+    /// use alloc::borrow::Cow;
+    /// use mutf8::MStr;
+    ///
+    /// let mstr = MStr::from_bytes(b"Hello, world!").unwrap();
+    /// assert_eq!(mstr.to_str(), Cow::Borrowed("Hello, world!"));
+    ///
+    /// let mstr = MStr::from_bytes(&[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81]).unwrap();
+    /// assert_eq!(mstr.to_str(), Cow::<str>::Owned("\u{10401}".to_string()));
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn to_str(&self) -> Cow<'_, str> {
+        match decode(&self.bytes) {
+            Ok(s) => s,
+            Err(_) => unreachable!("MStr holds valid MUTF-8"),
+        }
+    }
+}
+
+impl AsRef<[u8]> for MStr {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<MStr> for MStr {
+    #[inline]
+    fn as_ref(&self) -> &MStr {
+        self
+    }
+}
+
+impl Default for &MStr {
+    #[inline]
+    fn default() -> Self {
+        // SAFETY: An empty slice is valid MUTF-8.
+        unsafe { MStr::from_bytes_unchecked(&[]) }
+    }
+}
+
+impl<'a> From<&'a MStr> for Cow<'a, str> {
+    #[inline]
+    fn from(s: &'a MStr) -> Self {
+        s.to_str()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for &'a MStr {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        MStr::from_bytes(bytes)
+    }
+}
+
+impl fmt::Debug for MStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.to_str(), f)
+    }
+}
+
+impl fmt::Display for MStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.to_str(), f)
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{buffer::encode_append_mutf8, encode, encode_append, is_valid, MStr};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::String,
+    vec::Vec,
+};
+use core::{borrow::Borrow, fmt, ops::Deref};
+
+/// An owned buffer of bytes that is known to be valid MUTF-8.
+///
+/// `MString` is to [`MStr`] what [`String`] is to [`str`]. It is the natural
+/// way to hold onto MUTF-8 data, such as a Java string, for a long time without
+/// losing the fact that it has already been encoded.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::MString;
+///
+/// let mut mstring = MString::from("Hello");
+/// mstring.push('\0');
+/// mstring.push_str("\u{10401}");
+/// assert_eq!(
+///     mstring.as_bytes(),
+///     &[b'H', b'e', b'l', b'l', b'o', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81],
+/// );
+/// assert_eq!(mstring.into_string(), "Hello\0\u{10401}");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MString {
+    bytes: Vec<u8>,
+}
+
+impl MString {
+    /// Creates a new empty `MString`.
+    #[must_use]
+    #[inline]
+    pub const fn new() -> MString {
+        MString { bytes: Vec::new() }
+    }
+
+    /// Creates a new empty `MString` with at least the specified capacity in
+    /// bytes.
+    #[must_use]
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> MString {
+        MString {
+            bytes: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of bytes this `MString` can hold without
+    /// reallocating.
+    #[must_use]
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.bytes.reserve(additional);
+    }
+
+    /// Returns a borrowed [`MStr`] containing the entire `MString`.
+    #[must_use]
+    #[inline]
+    pub fn as_mstr(&self) -> &MStr {
+        // SAFETY: An `MString` always holds valid MUTF-8 data.
+        unsafe { MStr::from_bytes_unchecked(&self.bytes) }
+    }
+
+    /// Encodes a string slice and appends it to the end of this `MString`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::MString;
+    ///
+    /// let mut mstring = MString::from("foo");
+    /// mstring.push_str("\0bar");
+    /// assert_eq!(mstring.as_bytes(), b"foo\xC0\x80bar");
+    /// ```
+    #[inline]
+    pub fn push_str(&mut self, s: &str) {
+        encode_append(s, &mut self.bytes);
+    }
+
+    /// Encodes a character and appends it to the end of this `MString`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::MString;
+    ///
+    /// let mut mstring = MString::new();
+    /// mstring.push('a');
+    /// mstring.push('\0');
+    /// assert_eq!(mstring.as_bytes(), &[b'a', 0xC0, 0x80]);
+    /// ```
+    #[inline]
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Converts a vector of bytes to an `MString` without checking that it is
+    /// valid MUTF-8.
+    ///
+    /// # Safety
+    ///
+    /// The bytes passed in must be valid MUTF-8, as accepted by
+    /// [`MStr::from_bytes`].
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub(crate) const unsafe fn from_bytes_unchecked(bytes: Vec<u8>) -> MString {
+        MString { bytes }
+    }
+
+    /// Converts this `MString` into its MUTF-8 bytes.
+    #[must_use]
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Decodes this `MString` into a [`String`].
+    ///
+    /// If the MUTF-8 representation is identical to the UTF-8 one, the
+    /// underlying buffer is reused and no allocation takes place.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mutf8::MString;
+    ///
+    /// let mstring = MString::from("\0 and \u{10401}");
+    /// assert_eq!(mstring.into_string(), "\0 and \u{10401}");
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn into_string(self) -> String {
+        match self.to_str() {
+            Cow::Borrowed(_) => {
+                // SAFETY: `to_str` only borrows when the bytes are valid UTF-8.
+                unsafe { String::from_utf8_unchecked(self.bytes) }
+            }
+            Cow::Owned(s) => s,
+        }
+    }
+}
+
+impl Deref for MString {
+    type Target = MStr;
+
+    #[inline]
+    fn deref(&self) -> &MStr {
+        self.as_mstr()
+    }
+}
+
+impl AsRef<MStr> for MString {
+    #[inline]
+    fn as_ref(&self) -> &MStr {
+        self.as_mstr()
+    }
+}
+
+impl AsRef<[u8]> for MString {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<MStr> for MString {
+    #[inline]
+    fn borrow(&self) -> &MStr {
+        self.as_mstr()
+    }
+}
+
+impl ToOwned for MStr {
+    type Owned = MString;
+
+    #[inline]
+    fn to_owned(&self) -> MString {
+        MString {
+            bytes: self.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<&MStr> for MString {
+    #[inline]
+    fn from(s: &MStr) -> Self {
+        s.to_owned()
+    }
+}
+
+impl From<&str> for MString {
+    #[inline]
+    fn from(s: &str) -> Self {
+        MString {
+            bytes: encode(s).into_owned(),
+        }
+    }
+}
+
+impl From<String> for MString {
+    /// Converts a [`String`] to an `MString`, reusing its buffer if the string
+    /// is already valid MUTF-8.
+    #[inline]
+    fn from(s: String) -> Self {
+        let bytes = if is_valid(&s) {
+            s.into_bytes()
+        } else {
+            let mut bytes = Vec::new();
+            encode_append_mutf8(&s, &mut bytes);
+            bytes
+        };
+        MString { bytes }
+    }
+}
+
+impl From<MString> for Vec<u8> {
+    #[inline]
+    fn from(s: MString) -> Self {
+        s.into_bytes()
+    }
+}
+
+impl From<MString> for String {
+    #[inline]
+    fn from(s: MString) -> Self {
+        s.into_string()
+    }
+}
+
+impl fmt::Debug for MString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_mstr(), f)
+    }
+}
+
+impl fmt::Display for MString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_mstr(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_str_reserves_encoded_length() {
+        let s = "a\0\u{10401}";
+        let mut mstring = MString::with_capacity(crate::len(s));
+        let ptr = mstring.as_bytes().as_ptr();
+        mstring.push_str(s);
+        assert_eq!(
+            mstring.as_bytes(),
+            [b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81],
+        );
+        // Exactly the encoded length is needed, so nothing is reallocated.
+        assert_eq!(mstring.as_bytes().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn from_string() {
+        let s = String::from("abc");
+        let ptr = s.as_ptr();
+        assert_eq!(MString::from(s).as_bytes().as_ptr(), ptr);
+
+        let mstring = MString::from(String::from("\0\u{10401}"));
+        assert_eq!(
+            mstring.as_bytes(),
+            [0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81],
+        );
+    }
+}
@@ -0,0 +1,643 @@
+//! Reading and writing the NBT format of Minecraft: Java Edition.
+//!
+//! NBT (Named Binary Tag) is a tree of big-endian tags in which every tag name
+//! and every [`Tag::String`] is a string framed the way
+//! [`write_utf`](crate::write_utf) frames it. Strings are decoded by the same
+//! rules as [`decode`], and a string that is invalid MUTF-8 data is reported
+//! as a [`NbtError::Mutf8`] rather than being replaced.
+//!
+//! Compression is out of scope: NBT files are usually compressed with gzip,
+//! which has to be undone before they are read.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use mutf8::nbt::{self, Tag, TagType};
+//!
+//! let tag = Tag::Compound(vec![
+//!     ("name".to_string(), Tag::String("Steve\0".to_string())),
+//!     ("pos".to_string(), Tag::List(TagType::Double, vec![Tag::Double(0.5), Tag::Double(64.0)])),
+//! ]);
+//!
+//! let mut data = Vec::new();
+//! nbt::write("", &tag, &mut data).unwrap();
+//! assert_eq!(&data[..5], &[0x0A, 0x00, 0x00, 0x08, 0x00]);
+//!
+//! let (name, read_tag, read) = nbt::read(&data).unwrap();
+//! assert_eq!((name.as_str(), read), ("", data.len()));
+//! assert_eq!(read_tag, tag);
+//! ```
+
+use crate::{decode, encode, len, Error, TooLong};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// How deeply lists and compounds may be nested, which is the same limit
+/// Minecraft itself enforces.
+const MAX_DEPTH: usize = 512;
+
+/// The type of a [`Tag`].
+///
+/// Besides the types of every kind of tag, this includes [`TagType::End`],
+/// which marks the end of a compound, and is the element type of an empty
+/// list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum TagType {
+    /// `TAG_End`.
+    End = 0,
+    /// `TAG_Byte`.
+    Byte = 1,
+    /// `TAG_Short`.
+    Short = 2,
+    /// `TAG_Int`.
+    Int = 3,
+    /// `TAG_Long`.
+    Long = 4,
+    /// `TAG_Float`.
+    Float = 5,
+    /// `TAG_Double`.
+    Double = 6,
+    /// `TAG_Byte_Array`.
+    ByteArray = 7,
+    /// `TAG_String`.
+    String = 8,
+    /// `TAG_List`.
+    List = 9,
+    /// `TAG_Compound`.
+    Compound = 10,
+    /// `TAG_Int_Array`.
+    IntArray = 11,
+    /// `TAG_Long_Array`.
+    LongArray = 12,
+}
+
+impl TagType {
+    /// Returns the type with the given ID, or `None` if there is no such type.
+    #[must_use]
+    #[inline]
+    pub const fn from_id(id: u8) -> Option<TagType> {
+        Some(match id {
+            0 => TagType::End,
+            1 => TagType::Byte,
+            2 => TagType::Short,
+            3 => TagType::Int,
+            4 => TagType::Long,
+            5 => TagType::Float,
+            6 => TagType::Double,
+            7 => TagType::ByteArray,
+            8 => TagType::String,
+            9 => TagType::List,
+            10 => TagType::Compound,
+            11 => TagType::IntArray,
+            12 => TagType::LongArray,
+            _ => return None,
+        })
+    }
+
+    /// Returns the ID of the type, as it is written before a tag.
+    #[must_use]
+    #[inline]
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+}
+
+/// A tag of an NBT tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    /// `TAG_Byte`.
+    Byte(i8),
+    /// `TAG_Short`.
+    Short(i16),
+    /// `TAG_Int`.
+    Int(i32),
+    /// `TAG_Long`.
+    Long(i64),
+    /// `TAG_Float`.
+    Float(f32),
+    /// `TAG_Double`.
+    Double(f64),
+    /// `TAG_Byte_Array`.
+    ByteArray(Vec<i8>),
+    /// `TAG_String`.
+    String(String),
+    /// `TAG_List`, holding the type of its elements and the elements
+    /// themselves, all of which must be of that type.
+    List(TagType, Vec<Tag>),
+    /// `TAG_Compound`, holding its named tags in the order they appear in.
+    Compound(Vec<(String, Tag)>),
+    /// `TAG_Int_Array`.
+    IntArray(Vec<i32>),
+    /// `TAG_Long_Array`.
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// Returns the type of the tag.
+    #[must_use]
+    #[inline]
+    pub const fn tag_type(&self) -> TagType {
+        match self {
+            Tag::Byte(_) => TagType::Byte,
+            Tag::Short(_) => TagType::Short,
+            Tag::Int(_) => TagType::Int,
+            Tag::Long(_) => TagType::Long,
+            Tag::Float(_) => TagType::Float,
+            Tag::Double(_) => TagType::Double,
+            Tag::ByteArray(_) => TagType::ByteArray,
+            Tag::String(_) => TagType::String,
+            Tag::List(..) => TagType::List,
+            Tag::Compound(_) => TagType::Compound,
+            Tag::IntArray(_) => TagType::IntArray,
+            Tag::LongArray(_) => TagType::LongArray,
+        }
+    }
+}
+
+/// Reads a named tag from the start of a slice of bytes.
+///
+/// On success, the name and the tag are returned along with the number of
+/// bytes they take up.
+///
+/// # Errors
+///
+/// Returns [`NbtError`] if the data is malformed.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::nbt::{self, NbtError, Tag};
+///
+/// let data = &[0x03, 0x00, 0x01, b'a', 0x00, 0x00, 0x01, 0x00];
+/// let (name, tag, read) = nbt::read(data).unwrap();
+/// assert_eq!((name.as_str(), tag, read), ("a", Tag::Int(256), 8));
+///
+/// let error = nbt::read(&[0x08, 0x00, 0x01, 0xC0, 0x00, 0x00]).unwrap_err();
+/// assert!(matches!(error, NbtError::Mutf8(error) if error.valid_up_to() == 3));
+/// ```
+pub fn read(bytes: &[u8]) -> Result<(String, Tag, usize), NbtError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let tag_type = reader.tag_type()?;
+    if tag_type == TagType::End {
+        return Err(NbtError::InvalidTagType(0));
+    }
+    let name = reader.string()?;
+    let tag = reader.payload(tag_type, 0)?;
+    Ok((name, tag, reader.pos))
+}
+
+/// Writes a named tag, appending it to `out`.
+///
+/// # Errors
+///
+/// Returns [`NbtError`] if the tag cannot be written, in which case `out` is
+/// left untouched. This is the case if a string is longer than 65535 bytes in
+/// MUTF-8, if a list holds an element of the wrong type, if an array or a list
+/// is longer than [`i32::MAX`], or if lists and compounds are nested more than
+/// 512 levels deep.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::nbt::{self, Tag};
+///
+/// let mut data = Vec::new();
+/// nbt::write("a", &Tag::String("\0".to_string()), &mut data).unwrap();
+/// assert_eq!(data, &[0x08, 0x00, 0x01, b'a', 0x00, 0x02, 0xC0, 0x80]);
+/// ```
+pub fn write(name: &str, tag: &Tag, out: &mut Vec<u8>) -> Result<(), NbtError> {
+    let start = out.len();
+    out.push(tag.tag_type().id());
+    let result = write_string(name, out).and_then(|()| write_payload(tag, out, 0));
+    if result.is_err() {
+        out.truncate(start);
+    }
+    result
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NbtError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(NbtError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NbtError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn tag_type(&mut self) -> Result<TagType, NbtError> {
+        let [id] = self.array()?;
+        TagType::from_id(id).ok_or(NbtError::InvalidTagType(id))
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let len = usize::from(u16::from_be_bytes(self.array()?));
+        let start = self.pos;
+        let bytes = self.take(len)?;
+        match decode(bytes) {
+            Ok(s) => Ok(s.into_owned()),
+            Err(error) => Err(NbtError::Mutf8(error.offset(start))),
+        }
+    }
+
+    /// Reads the length of an array or a list whose elements take up at least
+    /// `min_size` bytes each.
+    fn len(&mut self, min_size: usize) -> Result<usize, NbtError> {
+        let len = i32::from_be_bytes(self.array()?);
+        let Ok(len) = usize::try_from(len) else {
+            return Err(NbtError::NegativeLength(len));
+        };
+        // Checked up front so that a bogus length cannot allocate too much.
+        if len.saturating_mul(min_size) > self.bytes.len() - self.pos {
+            return Err(NbtError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn payload(&mut self, tag_type: TagType, depth: usize) -> Result<Tag, NbtError> {
+        macro_rules! array {
+            ($ty:ty) => {{
+                let len = self.len(core::mem::size_of::<$ty>())?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    array.push(<$ty>::from_be_bytes(self.array()?));
+                }
+                array
+            }};
+        }
+
+        Ok(match tag_type {
+            TagType::End => return Err(NbtError::InvalidTagType(0)),
+            TagType::Byte => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            TagType::Short => Tag::Short(i16::from_be_bytes(self.array()?)),
+            TagType::Int => Tag::Int(i32::from_be_bytes(self.array()?)),
+            TagType::Long => Tag::Long(i64::from_be_bytes(self.array()?)),
+            TagType::Float => Tag::Float(f32::from_be_bytes(self.array()?)),
+            TagType::Double => Tag::Double(f64::from_be_bytes(self.array()?)),
+            TagType::ByteArray => Tag::ByteArray(array!(i8)),
+            TagType::String => Tag::String(self.string()?),
+            TagType::List => {
+                let depth = nest(depth)?;
+                let element_type = self.tag_type()?;
+                let len = self.len(1)?;
+                if element_type == TagType::End && len > 0 {
+                    return Err(NbtError::InvalidTagType(0));
+                }
+                let mut list = Vec::with_capacity(len);
+                for _ in 0..len {
+                    list.push(self.payload(element_type, depth)?);
+                }
+                Tag::List(element_type, list)
+            }
+            TagType::Compound => {
+                let depth = nest(depth)?;
+                let mut compound = Vec::new();
+                loop {
+                    let tag_type = self.tag_type()?;
+                    if tag_type == TagType::End {
+                        break;
+                    }
+                    let name = self.string()?;
+                    compound.push((name, self.payload(tag_type, depth)?));
+                }
+                Tag::Compound(compound)
+            }
+            TagType::IntArray => Tag::IntArray(array!(i32)),
+            TagType::LongArray => Tag::LongArray(array!(i64)),
+        })
+    }
+}
+
+/// Returns the depth inside a list or a compound at `depth`, or an error if it
+/// is too deep.
+fn nest(depth: usize) -> Result<usize, NbtError> {
+    if depth < MAX_DEPTH {
+        Ok(depth + 1)
+    } else {
+        Err(NbtError::TooDeep)
+    }
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) -> Result<(), NbtError> {
+    let encoded_len = len(s);
+    let Ok(prefix) = u16::try_from(encoded_len) else {
+        return Err(NbtError::TooLong(TooLong::new(encoded_len)));
+    };
+    out.extend_from_slice(&prefix.to_be_bytes());
+    out.extend_from_slice(&encode(s));
+    Ok(())
+}
+
+fn write_len(len: usize, out: &mut Vec<u8>) -> Result<(), NbtError> {
+    let len = i32::try_from(len).map_err(|_| NbtError::LengthOverflow(len))?;
+    out.extend_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+fn write_payload(tag: &Tag, out: &mut Vec<u8>, depth: usize) -> Result<(), NbtError> {
+    macro_rules! array {
+        ($array:expr) => {{
+            write_len($array.len(), out)?;
+            for value in $array {
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+        }};
+    }
+
+    match tag {
+        Tag::Byte(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::Short(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::Int(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::Long(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::Float(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::Double(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Tag::ByteArray(array) => array!(array),
+        Tag::String(s) => write_string(s, out)?,
+        Tag::List(element_type, list) => {
+            let depth = nest(depth)?;
+            out.push(element_type.id());
+            write_len(list.len(), out)?;
+            for element in list {
+                if element.tag_type() != *element_type {
+                    return Err(NbtError::ListTypeMismatch {
+                        expected: *element_type,
+                        found: element.tag_type(),
+                    });
+                }
+                write_payload(element, out, depth)?;
+            }
+        }
+        Tag::Compound(compound) => {
+            let depth = nest(depth)?;
+            for (name, tag) in compound {
+                out.push(tag.tag_type().id());
+                write_string(name, out)?;
+                write_payload(tag, out, depth)?;
+            }
+            out.push(TagType::End.id());
+        }
+        Tag::IntArray(array) => array!(array),
+        Tag::LongArray(array) => array!(array),
+    }
+    Ok(())
+}
+
+/// An error thrown when NBT data cannot be read or written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NbtError {
+    /// The input ended before the end of the tag.
+    UnexpectedEnd,
+    /// A tag type ID is unknown, or is that of [`TagType::End`] where a tag
+    /// was expected.
+    InvalidTagType(u8),
+    /// The length of an array or a list is negative.
+    NegativeLength(i32),
+    /// Lists and compounds are nested more than 512 levels deep.
+    TooDeep,
+    /// A string is invalid MUTF-8 data. The position given by
+    /// [`valid_up_to`](Error::valid_up_to) is relative to the start of the
+    /// input.
+    Mutf8(Error),
+    /// A string to be written is longer than 65535 bytes in MUTF-8.
+    TooLong(TooLong),
+    /// An array or a list to be written is longer than [`i32::MAX`].
+    LengthOverflow(usize),
+    /// A list to be written holds an element of the wrong type.
+    ListTypeMismatch {
+        /// The element type of the list.
+        expected: TagType,
+        /// The type of the element.
+        found: TagType,
+    },
+}
+
+impl fmt::Display for NbtError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            NbtError::UnexpectedEnd => f.write_str("unexpected end of NBT data"),
+            NbtError::InvalidTagType(id) => write!(f, "invalid tag type {id}"),
+            NbtError::NegativeLength(len) => write!(f, "negative length {len}"),
+            NbtError::TooDeep => write!(f, "tags nested more than {MAX_DEPTH} levels deep"),
+            NbtError::Mutf8(error) => error.fmt(f),
+            NbtError::TooLong(error) => error.fmt(f),
+            NbtError::LengthOverflow(len) => write!(f, "length {len} does not fit in an i32"),
+            NbtError::ListTypeMismatch { expected, found } => {
+                write!(f, "list of {expected:?} tags holds a {found:?} tag")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl std::error::Error for NbtError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NbtError::Mutf8(error) => Some(error),
+            NbtError::TooLong(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{borrow::ToOwned, vec};
+
+    /// A compound holding a tag of every type.
+    fn every_tag() -> Tag {
+        Tag::Compound(vec![
+            ("byte".to_owned(), Tag::Byte(-2)),
+            ("short".to_owned(), Tag::Short(-300)),
+            ("int".to_owned(), Tag::Int(70_000)),
+            ("long".to_owned(), Tag::Long(-5_000_000_000)),
+            ("float".to_owned(), Tag::Float(0.5)),
+            ("double".to_owned(), Tag::Double(-64.25)),
+            ("bytes".to_owned(), Tag::ByteArray(vec![1, -1, 0])),
+            (
+                "string".to_owned(),
+                Tag::String("\0\u{E9}\u{1F600}".to_owned()),
+            ),
+            (
+                "list".to_owned(),
+                Tag::List(TagType::Short, vec![Tag::Short(1), Tag::Short(2)]),
+            ),
+            ("empty".to_owned(), Tag::List(TagType::End, vec![])),
+            (
+                "compound".to_owned(),
+                Tag::Compound(vec![("\0".to_owned(), Tag::Byte(1))]),
+            ),
+            (
+                "ints".to_owned(),
+                Tag::IntArray(vec![i32::MIN, 0, i32::MAX]),
+            ),
+            ("longs".to_owned(), Tag::LongArray(vec![i64::MIN, i64::MAX])),
+        ])
+    }
+
+    /// Returns a list nested `depth` levels deep, counting itself.
+    fn nested_lists(depth: usize) -> Tag {
+        let mut tag = Tag::List(TagType::End, vec![]);
+        for _ in 1..depth {
+            tag = Tag::List(TagType::List, vec![tag]);
+        }
+        tag
+    }
+
+    #[test]
+    fn round_trip_every_tag() {
+        let tag = every_tag();
+        let Tag::Compound(compound) = &tag else {
+            unreachable!()
+        };
+        for (name, tag) in compound.iter().chain([&("root".to_owned(), tag.clone())]) {
+            let mut data = Vec::new();
+            write(name, tag, &mut data).unwrap();
+            assert_eq!(data[0], tag.tag_type().id());
+            let (read_name, read_tag, read_len) = read(&data).unwrap();
+            assert_eq!(&read_name, name);
+            assert_eq!(&read_tag, tag);
+            assert_eq!(read_len, data.len());
+        }
+
+        let mut data = Vec::new();
+        write("", &Tag::String("\0\u{1F600}".to_owned()), &mut data).unwrap();
+        assert_eq!(
+            data,
+            [0x08, 0x00, 0x00, 0x00, 0x08, 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let mut data = Vec::new();
+        write("root", &every_tag(), &mut data).unwrap();
+        for end in 0..data.len() {
+            assert_eq!(read(&data[..end]), Err(NbtError::UnexpectedEnd), "{end}");
+        }
+
+        // Cut off within or right after each kind of length prefix.
+        for data in [
+            &[0x01, 0x00][..],
+            &[0x01, 0x00, 0x01],
+            &[0x08, 0x00, 0x00, 0x00, 0x02, b'a'],
+            &[0x07, 0x00, 0x00, 0x00, 0x00, 0x00],
+            &[0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01],
+            &[0x09, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+            &[0x09, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x01],
+            &[0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+            &[0x0C, 0x00, 0x00, 0x7F, 0xFF, 0xFF, 0xFF],
+        ] {
+            assert_eq!(read(data), Err(NbtError::UnexpectedEnd), "{data:02X?}");
+        }
+    }
+
+    #[test]
+    fn negative_length() {
+        for data in [
+            &[0x07, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF][..],
+            &[0x09, 0x00, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFE],
+            &[0x0B, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00],
+            &[0x0C, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFD],
+        ] {
+            let expected = i32::from_be_bytes(data[data.len() - 4..].try_into().unwrap());
+            assert_eq!(
+                read(data),
+                Err(NbtError::NegativeLength(expected)),
+                "{data:02X?}"
+            );
+        }
+    }
+
+    #[test]
+    fn list_type_mismatch() {
+        let mut data = vec![0xAA];
+        let tag = Tag::Compound(vec![(
+            "list".to_owned(),
+            Tag::List(TagType::Int, vec![Tag::Int(1), Tag::Byte(2)]),
+        )]);
+        assert_eq!(
+            write("", &tag, &mut data),
+            Err(NbtError::ListTypeMismatch {
+                expected: TagType::Int,
+                found: TagType::Byte,
+            }),
+        );
+        assert_eq!(data, [0xAA]);
+
+        // A list of `TAG_End` can only be empty.
+        let data = [0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00];
+        assert_eq!(read(&data), Err(NbtError::InvalidTagType(0)));
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut data = Vec::new();
+        write("", &nested_lists(MAX_DEPTH), &mut data).unwrap();
+        assert_eq!(read(&data).unwrap().1, nested_lists(MAX_DEPTH));
+
+        let mut data = Vec::new();
+        assert_eq!(
+            write("", &nested_lists(MAX_DEPTH + 1), &mut data),
+            Err(NbtError::TooDeep)
+        );
+        assert!(data.is_empty());
+
+        // The same list as above, one level deeper, built by hand.
+        let mut data = vec![0x09, 0x00, 0x00];
+        for _ in 1..=MAX_DEPTH {
+            data.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01]);
+        }
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(read(&data), Err(NbtError::TooDeep));
+    }
+
+    #[test]
+    fn invalid_mutf8() {
+        // In the name of the root tag.
+        let error = read(&[0x01, 0x00, 0x02, b'a', 0x80, 0x05]).unwrap_err();
+        let NbtError::Mutf8(error) = error else {
+            panic!("{error:?}")
+        };
+        assert_eq!((error.valid_up_to(), error.error_len()), (4, Some(1)));
+
+        // In a `TAG_String` inside a compound, reported relative to the start
+        // of the input.
+        let data = [
+            0x0A, 0x00, 0x00, 0x08, 0x00, 0x01, b's', 0x00, 0x03, b'a', 0x80, b'b', 0x00,
+        ];
+        let error = read(&data).unwrap_err();
+        let NbtError::Mutf8(error) = error else {
+            panic!("{error:?}")
+        };
+        assert_eq!((error.valid_up_to(), error.error_len()), (10, Some(1)));
+        assert_eq!(error.kind(), crate::ErrorKind::InvalidByte);
+
+        // A 4-byte sequence is accepted, as it is by `decode`.
+        let data = [0x08, 0x00, 0x00, 0x00, 0x04, 0xF0, 0x9F, 0x98, 0x80];
+        assert_eq!(read(&data).unwrap().1, Tag::String("\u{1F600}".to_owned()));
+    }
+}
@@ -0,0 +1,151 @@
+//! Scanning of individual MUTF-8 sequences.
+//!
+//! MUTF-8 is a byte serialization of UTF-16 code units: every code unit is
+//! written as a 1, 2 or 3-byte sequence, and a supplementary character is
+//! written as the two 3-byte sequences of its surrogate pair. The functions in
+//! this module decode one such sequence at a time and are shared by every
+//! decoder in the crate.
+
+use crate::error::{ErrorKind, Invalid};
+
+/// Decodes the UTF-16 code unit at the start of `bytes`, returning it along
+/// with the number of bytes it occupies.
+///
+/// Surrogate code units are returned as-is; pairing them is up to the caller.
+/// If `strict` is `true`, a raw null byte is rejected, as it is never written
+/// by a MUTF-8 encoder.
+///
+/// `bytes` must not be empty.
+#[inline]
+pub(crate) fn next_code_unit(bytes: &[u8], strict: bool) -> Result<(u16, usize), Invalid> {
+    let first = bytes[0];
+    match first {
+        0x00 if strict => Err(Invalid::new(ErrorKind::NullByte, 1)),
+        0x00..=0x7F => Ok((u16::from(first), 1)),
+        0xC0 => match bytes.get(1) {
+            Some(&0x80) => Ok((0, 2)),
+            Some(&byte) if is_continuation_byte(byte) => Err(Invalid::new(ErrorKind::Overlong, 1)),
+            Some(_) => Err(Invalid::new(ErrorKind::InvalidNullPair, 1)),
+            None => Err(Invalid::INCOMPLETE),
+        },
+        0xC1 => Err(Invalid::new(ErrorKind::Overlong, 1)),
+        0xC2..=0xDF => {
+            let second = continuation(bytes, 1)?;
+            Ok((u16::from(first & 0x1F) << 6 | u16::from(second & 0x3F), 2))
+        }
+        0xE0..=0xEF => {
+            match (first, bytes.get(1)) {
+                (_, None) => return Err(Invalid::INCOMPLETE),
+                (0xE0, Some(0xA0..=0xBF)) | (0xE1..=0xEF, Some(0x80..=0xBF)) => {}
+                (0xE0, Some(0x80..=0x9F)) => return Err(Invalid::new(ErrorKind::Overlong, 1)),
+                _ => return Err(Invalid::new(ErrorKind::InvalidContinuation, 1)),
+            }
+            let second = bytes[1];
+            let third = continuation(bytes, 2)?;
+            Ok((
+                u16::from(first & 0x0F) << 12
+                    | u16::from(second & 0x3F) << 6
+                    | u16::from(third & 0x3F),
+                3,
+            ))
+        }
+        0xF0..=0xF4 => Err(four_byte_sequence(bytes)),
+        _ => Err(Invalid::new(ErrorKind::InvalidByte, 1)),
+    }
+}
+
+/// Returns the continuation byte at `index`, or the error describing why there
+/// is none.
+#[inline]
+fn continuation(bytes: &[u8], index: u8) -> Result<u8, Invalid> {
+    match bytes.get(usize::from(index)) {
+        Some(&byte) if is_continuation_byte(byte) => Ok(byte),
+        Some(_) => Err(Invalid::new(ErrorKind::InvalidContinuation, index)),
+        None => Err(Invalid::INCOMPLETE),
+    }
+}
+
+/// Describes a sequence starting with a 4-byte UTF-8 lead byte. Such a sequence
+/// is never valid, so its length is that of the longest prefix UTF-8 would
+/// accept. It is only reported as incomplete if the input ends before that
+/// prefix does.
+#[cold]
+fn four_byte_sequence(bytes: &[u8]) -> Invalid {
+    let mut len = 1;
+    match (bytes[0], bytes.get(1)) {
+        (_, None) => return Invalid::INCOMPLETE,
+        (0xF0, Some(0x90..=0xBF))
+        | (0xF1..=0xF3, Some(0x80..=0xBF))
+        | (0xF4, Some(0x80..=0x8F)) => {
+            len = 2;
+            while len < 4 {
+                match bytes.get(usize::from(len)) {
+                    Some(&byte) if is_continuation_byte(byte) => len += 1,
+                    Some(_) => break,
+                    None => return Invalid::INCOMPLETE,
+                }
+            }
+        }
+        _ => {}
+    }
+    Invalid::new(ErrorKind::FourByteSequence, len)
+}
+
+/// Decodes the code point at the start of `bytes`, returning it along with the
+/// number of bytes it occupies.
+///
+/// A surrogate pair is combined into a single supplementary code point, and a
+/// surrogate that is not part of a pair is rejected.
+///
+/// `bytes` must not be empty.
+#[inline]
+pub(crate) fn next_code_point(bytes: &[u8], strict: bool) -> Result<(u32, usize), Invalid> {
+    let (unit, width) = next_code_unit(bytes, strict)?;
+    match unit {
+        0xD800..=0xDBFF => {
+            let rest = &bytes[width..];
+            if rest.is_empty() {
+                return Err(Invalid::INCOMPLETE);
+            }
+            match next_code_unit(rest, strict) {
+                Ok((low @ 0xDC00..=0xDFFF, low_width)) => {
+                    Ok((combine_surrogates(unit, low), width + low_width))
+                }
+                Err(Invalid { len: None, .. }) if may_start_low_surrogate(rest) => {
+                    Err(Invalid::INCOMPLETE)
+                }
+                _ => Err(Invalid::new(ErrorKind::UnpairedSurrogate, 3)),
+            }
+        }
+        0xDC00..=0xDFFF => Err(Invalid::new(ErrorKind::UnpairedSurrogate, 3)),
+        _ => Ok((u32::from(unit), width)),
+    }
+}
+
+/// Returns `true` if the truncated `bytes` could still become an encoded low
+/// surrogate once more input arrives.
+#[inline]
+fn may_start_low_surrogate(bytes: &[u8]) -> bool {
+    bytes[0] == 0xED && bytes.get(1).is_none_or(|byte| (0xB0..=0xBF).contains(byte))
+}
+
+/// Decodes a 4-byte sequence that [`next_code_unit`] reported as a
+/// [`FourByteSequence`](ErrorKind::FourByteSequence) of length 4.
+#[inline]
+pub(crate) fn four_byte_code_point(bytes: &[u8]) -> u32 {
+    u32::from(bytes[0] & 0x07) << 18
+        | u32::from(bytes[1] & 0x3F) << 12
+        | u32::from(bytes[2] & 0x3F) << 6
+        | u32::from(bytes[3] & 0x3F)
+}
+
+/// Combines a high and a low surrogate into a supplementary code point.
+#[inline]
+pub(crate) fn combine_surrogates(high: u16, low: u16) -> u32 {
+    0x10000 + ((u32::from(high) - 0xD800) << 10 | (u32::from(low) - 0xDC00))
+}
+
+#[inline]
+pub(crate) fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
@@ -0,0 +1,315 @@
+//! Support for serializing MUTF-8 strings with [`serde`].
+//!
+//! [`MStr`] and [`MString`] are serialized as their MUTF-8 bytes. A plain
+//! string field can be serialized the same way with the [`bytes`] helper
+//! module. The [`data`] module is a data format of its own, which reads and
+//! writes the wire format of `java.io.DataOutput`.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use mutf8::{MStr, MString};
+//! use serde_test::{assert_de_tokens, assert_tokens, Token};
+//!
+//! let mstring = MString::from("a\0");
+//! assert_tokens(&mstring, &[Token::Bytes(&[b'a', 0xC0, 0x80])]);
+//!
+//! // An `MStr` can be borrowed from the input.
+//! let mstr = MStr::from_bytes(&[b'a', 0xC0, 0x80]).unwrap();
+//! assert_tokens(&mstr, &[Token::BorrowedBytes(&[b'a', 0xC0, 0x80])]);
+//!
+//! // A string is accepted in place of bytes, and is encoded to MUTF-8.
+//! assert_de_tokens(&mstring, &[Token::Str("a\0")]);
+//! ```
+
+use crate::{MStr, MString};
+use ::serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+pub mod data;
+
+impl Serialize for MStr {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+impl Serialize for MString {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a MStr {
+    /// Deserializes an `MStr` borrowed from the input, which fails if the input
+    /// cannot lend out its bytes or if they are not valid MUTF-8.
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MStrVisitor;
+
+        impl<'de> Visitor<'de> for MStrVisitor {
+            type Value = &'de MStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("borrowed MUTF-8 bytes")
+            }
+
+            fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                MStr::from_bytes(v).map_err(E::custom)
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                self.visit_borrowed_bytes(v.as_bytes())
+            }
+        }
+
+        deserializer.deserialize_bytes(MStrVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for MString {
+    /// Deserializes an `MString` from MUTF-8 bytes, which must be valid. A
+    /// string is accepted as well, and is encoded to MUTF-8.
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MStringVisitor;
+
+        impl<'de> Visitor<'de> for MStringVisitor {
+            type Value = MString;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("MUTF-8 bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                MStr::from_bytes(v).map(MString::from).map_err(E::custom)
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                MStr::from_bytes(&v).map_err(E::custom)?;
+                // SAFETY: The bytes were validated above.
+                Ok(unsafe { MString::from_bytes_unchecked(v) })
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(MString::from(v))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(MString::from(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                self.visit_byte_buf(bytes_from_seq(&mut seq)?)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(MStringVisitor)
+    }
+}
+
+/// Collects a sequence of bytes, which is how formats without native support
+/// for bytes, such as JSON, represent them.
+fn bytes_from_seq<'de, A: SeqAccess<'de>>(seq: &mut A) -> Result<Vec<u8>, A::Error> {
+    let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+    while let Some(byte) = seq.next_element()? {
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+pub mod bytes {
+    //! Serializes a string field as MUTF-8 bytes.
+    //!
+    //! Use this module with `#[serde(with = "mutf8::serde::bytes")]` on a field
+    //! of type [`String`], or of type [`Cow<str>`] to borrow from the input
+    //! whenever [`decode`] can.
+    //!
+    //! The string is serialized as bytes encoded by [`encode`], and
+    //! deserialized from bytes by [`decode`], so the same rules apply. A string
+    //! is accepted in place of bytes as well.
+    //!
+    //! # Examples
+    //!
+    //! Basic usage:
+    //!
+    //! ```
+    //! # extern crate alloc;
+    //! use alloc::borrow::Cow;
+    //! use serde::{Deserialize, Serialize};
+    //! use serde_test::{assert_tokens, Token};
+    //!
+    //! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    //! struct Entry<'a> {
+    //!     #[serde(with = "mutf8::serde::bytes")]
+    //!     owned: String,
+    //!     #[serde(borrow, with = "mutf8::serde::bytes")]
+    //!     borrowed: Cow<'a, str>,
+    //! }
+    //!
+    //! let entry = Entry { owned: "a\0".to_string(), borrowed: Cow::Borrowed("b") };
+    //! assert_tokens(
+    //!     &entry,
+    //!     &[
+    //!         Token::Struct { name: "Entry", len: 2 },
+    //!         Token::Str("owned"),
+    //!         Token::BorrowedBytes(&[b'a', 0xC0, 0x80]),
+    //!         Token::Str("borrowed"),
+    //!         Token::BorrowedBytes(b"b"),
+    //!         Token::StructEnd,
+    //!     ],
+    //! );
+    //! ```
+    //!
+    //! [`Cow<str>`]: alloc::borrow::Cow
+    //! [`decode`]: crate::decode
+    //! [`encode`]: crate::encode
+
+    use crate::{decode, encode};
+    use ::serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserializer, Serializer,
+    };
+    use alloc::{borrow::Cow, string::String, vec::Vec};
+    use core::{fmt, marker::PhantomData};
+
+    /// Serializes a string as MUTF-8 bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the serializer fails.
+    #[inline]
+    pub fn serialize<S: Serializer>(s: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&encode(s))
+    }
+
+    /// Deserializes a string from MUTF-8 bytes, borrowing from the input when
+    /// it is already valid UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the deserializer fails, or if the bytes are invalid
+    /// MUTF-8 data.
+    #[inline]
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<Cow<'de, str>>,
+    {
+        struct BytesVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: From<Cow<'de, str>>> Visitor<'de> for BytesVisitor<T> {
+            type Value = T;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("MUTF-8 bytes")
+            }
+
+            fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<T, E> {
+                decode(v).map(T::from).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+                let s = decode(v).map_err(E::custom)?;
+                Ok(T::from(Cow::Owned(s.into_owned())))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<T, E> {
+                let s = match String::from_utf8(v) {
+                    Ok(s) => s,
+                    Err(error) => decode(error.as_bytes()).map_err(E::custom)?.into_owned(),
+                };
+                Ok(T::from(Cow::Owned(s)))
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<T, E> {
+                Ok(T::from(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+                Ok(T::from(Cow::Owned(String::from(v))))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<T, E> {
+                Ok(T::from(Cow::Owned(v)))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+                self.visit_byte_buf(super::bytes_from_seq(&mut seq)?)
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::de::value::{BorrowedBytesDeserializer, BytesDeserializer, Error as ValueError};
+    use alloc::{borrow::Cow, string::ToString};
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    fn deserialize_bytes(bytes: &[u8]) -> Result<Cow<'_, str>, ValueError> {
+        bytes::deserialize(BorrowedBytesDeserializer::new(bytes))
+    }
+
+    #[test]
+    fn bytes_borrows_canonical_input() {
+        assert!(matches!(
+            deserialize_bytes(b"plain \xC3\xA9"),
+            Ok(Cow::Borrowed("plain \u{E9}"))
+        ));
+        assert!(matches!(deserialize_bytes(b""), Ok(Cow::Borrowed(""))));
+
+        for (bytes, expected) in [
+            (&[b'a', 0xC0, 0x80][..], "a\0"),
+            (&[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, b'b'], "\u{1F600}b"),
+        ] {
+            let Ok(Cow::Owned(s)) = deserialize_bytes(bytes) else {
+                panic!("{bytes:02X?}");
+            };
+            assert_eq!(s, expected);
+        }
+
+        // Bytes that are not borrowed from the input are always copied.
+        let s: Cow<'_, str> =
+            bytes::deserialize(BytesDeserializer::<ValueError>::new(b"a")).unwrap();
+        assert!(matches!(s, Cow::Owned(s) if s == "a"));
+
+        let error = deserialize_bytes(&[b'a', 0x80]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            crate::decode(&[b'a', 0x80]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn round_trip_rejects_invalid_mutf8() {
+        const BYTES: &[u8] = &[b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+        let mstring = MString::from("a\0\u{1F600}");
+        assert_eq!(mstring.as_bytes(), BYTES);
+        assert_tokens(&mstring, &[Token::Bytes(BYTES)]);
+        assert_tokens(&mstring.as_mstr(), &[Token::BorrowedBytes(BYTES)]);
+
+        for bytes in [
+            &[b'a', 0x00][..],
+            &[0xF0, 0x9F, 0x98, 0x80],
+            &[0xED, 0xA0, 0xBD, b'b'],
+            &[b'a', 0xC0],
+        ] {
+            let message = MStr::from_bytes(bytes).unwrap_err().to_string();
+            assert_de_tokens_error::<&MStr>(&[Token::BorrowedBytes(bytes)], &message);
+            assert_de_tokens_error::<MString>(&[Token::Bytes(bytes)], &message);
+            assert_de_tokens_error::<MString>(&[Token::ByteBuf(bytes)], &message);
+        }
+    }
+}
@@ -0,0 +1,1057 @@
+//! A [`serde`] data format for the wire format of `java.io.DataOutput`.
+//!
+//! Values are written the way a hand-written `writeObject` method built on
+//! `DataOutputStream` would write them, so a Rust type can be exchanged
+//! byte-for-byte with Java code that reads it back with `DataInputStream`, and
+//! the other way around. The format is not self-describing, so the type to
+//! deserialize must be known ahead of time.
+//!
+//! Each type is mapped as follows:
+//!
+//! | Rust               | Java                                             |
+//! |--------------------|--------------------------------------------------|
+//! | `bool`             | `writeBoolean`                                   |
+//! | `i8`, `u8`         | `writeByte`                                      |
+//! | `i16`, `u16`       | `writeShort`                                     |
+//! | `i32`, `u32`       | `writeInt`                                       |
+//! | `i64`, `u64`       | `writeLong`                                      |
+//! | `i128`, `u128`     | 16 bytes, big-endian                             |
+//! | `f32`              | `writeFloat`                                     |
+//! | `f64`              | `writeDouble`                                    |
+//! | `char`             | `writeChar`                                      |
+//! | `str`, `String`    | `writeUTF`                                       |
+//! | bytes              | `writeInt` of the length, then `write`           |
+//! | `Option<T>`        | `writeBoolean` of whether it is `Some`, then `T` |
+//! | `()`, unit structs | nothing                                          |
+//! | tuples, structs    | each field in order                              |
+//! | sequences, maps    | `writeInt` of the length, then each element      |
+//! | enums              | `writeInt` of the variant index, then its fields |
+//!
+//! Strings are framed by [`write_utf`] and read back by [`read_utf`], and a
+//! string that is valid UTF-8 is borrowed from the input. A `char` can only be
+//! serialized if it fits in a single UTF-16 code unit, as Java's `char` can
+//! hold nothing more.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Player<'a> {
+//!     name: &'a str,
+//!     health: f32,
+//!     inventory: Vec<u16>,
+//! }
+//!
+//! let player = Player { name: "Steve", health: 20.0, inventory: vec![1, 3] };
+//! let bytes = mutf8::serde::data::to_vec(&player).unwrap();
+//! assert_eq!(
+//!     bytes,
+//!     &[
+//!         0x00, 0x05, b'S', b't', b'e', b'v', b'e', // writeUTF("Steve")
+//!         0x41, 0xA0, 0x00, 0x00, // writeFloat(20.0f)
+//!         0x00, 0x00, 0x00, 0x02, // writeInt(2)
+//!         0x00, 0x01, 0x00, 0x03, // writeShort(1), writeShort(3)
+//!     ],
+//! );
+//!
+//! let read: Player = mutf8::serde::data::from_slice(&bytes).unwrap();
+//! assert_eq!(read, player);
+//! ```
+//!
+//! The bytes above are what the following Java code writes:
+//!
+//! ```java
+//! out.writeUTF("Steve");
+//! out.writeFloat(20.0f);
+//! out.writeInt(2);
+//! out.writeShort(1);
+//! out.writeShort(3);
+//! ```
+//!
+//! [`read_utf`]: crate::read_utf
+//! [`write_utf`]: crate::write_utf
+
+use crate::{decode, write_utf, Error, TooLong};
+use ::serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+    Deserialize,
+};
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+/// Serializes a value to a vector of bytes.
+///
+/// # Errors
+///
+/// Returns [`DataError`] if the value cannot be represented in the format.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let bytes = mutf8::serde::data::to_vec(&("a\0", Some(true), -2_i32)).unwrap();
+/// assert_eq!(bytes, &[0x00, 0x03, b'a', 0xC0, 0x80, 0x01, 0x01, 0xFF, 0xFF, 0xFF, 0xFE]);
+/// ```
+#[inline]
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, DataError> {
+    let mut out = Vec::new();
+    value.serialize(&mut Serializer::new(&mut out))?;
+    Ok(out)
+}
+
+/// Deserializes a value from a slice of bytes, all of which must be used.
+///
+/// # Errors
+///
+/// Returns [`DataError`] if the bytes are not a valid representation of the
+/// value, or if any bytes are left over.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::serde::data::{self, DataError};
+///
+/// let value: (&str, char) = data::from_slice(&[0x00, 0x02, b'h', b'i', 0x00, 0x21]).unwrap();
+/// assert_eq!(value, ("hi", '!'));
+///
+/// let error = data::from_slice::<u16>(&[0x00, 0x01, 0x02]).unwrap_err();
+/// assert_eq!(error, DataError::TrailingBytes);
+/// ```
+#[inline]
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, DataError> {
+    let mut deserializer = Deserializer::new(bytes);
+    let value = T::deserialize(&mut deserializer)?;
+    if deserializer.remaining().is_empty() {
+        Ok(value)
+    } else {
+        Err(DataError::TrailingBytes)
+    }
+}
+
+/// A serializer that appends values to a vector of bytes.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::serde::data::Serializer;
+/// use serde::Serialize;
+///
+/// let mut out = Vec::new();
+/// 'A'.serialize(&mut Serializer::new(&mut out)).unwrap();
+/// 1_u8.serialize(&mut Serializer::new(&mut out)).unwrap();
+/// assert_eq!(out, &[0x00, 0x41, 0x01]);
+/// ```
+#[derive(Debug)]
+pub struct Serializer<'a> {
+    out: &'a mut Vec<u8>,
+}
+
+impl<'a> Serializer<'a> {
+    /// Creates a serializer that appends to `out`.
+    #[must_use]
+    #[inline]
+    pub fn new(out: &'a mut Vec<u8>) -> Self {
+        Serializer { out }
+    }
+
+    fn write_len(&mut self, len: usize) -> Result<(), DataError> {
+        let Ok(len) = i32::try_from(len) else {
+            return Err(DataError::LengthOverflow(len));
+        };
+        self.out.extend_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), DataError> {
+        self.out.push(u8::from(v));
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), DataError> {
+        self.out.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), DataError> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), DataError> {
+        let Ok(unit) = u16::try_from(u32::from(v)) else {
+            return Err(DataError::CharOutOfRange(v));
+        };
+        self.out.extend_from_slice(&unit.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), DataError> {
+        write_utf(v, self.out).map_err(DataError::TooLong)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), DataError> {
+        self.write_len(v.len())?;
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), DataError> {
+        self.serialize_bool(false)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), DataError> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), DataError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), DataError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), DataError> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        self.out.extend_from_slice(&variant_index.to_be_bytes());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, DataError> {
+        self.write_len(len.ok_or(DataError::UnknownLength)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, DataError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, DataError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, DataError> {
+        self.out.extend_from_slice(&variant_index.to_be_bytes());
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, DataError> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, DataError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self, DataError> {
+        self.serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), DataError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = DataError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), DataError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), DataError> {
+        Ok(())
+    }
+}
+
+/// A deserializer that reads values from a slice of bytes.
+///
+/// Unlike [`from_slice`], the deserializer does not care whether any bytes are
+/// left over, so it can read several values one after another.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::serde::data::Deserializer;
+/// use serde::Deserialize;
+///
+/// let mut deserializer = Deserializer::new(&[0x00, 0x41, 0x01]);
+/// assert_eq!(char::deserialize(&mut deserializer).unwrap(), 'A');
+/// assert_eq!(u8::deserialize(&mut deserializer).unwrap(), 1);
+/// assert!(deserializer.remaining().is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    bytes: &'de [u8],
+    pos: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer that reads from the start of `bytes`.
+    #[must_use]
+    #[inline]
+    pub fn new(bytes: &'de [u8]) -> Self {
+        Deserializer { bytes, pos: 0 }
+    }
+
+    /// Returns the bytes that have not been read yet.
+    #[must_use]
+    #[inline]
+    pub fn remaining(&self) -> &'de [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], DataError> {
+        let Some(taken) = self.remaining().get(..len) else {
+            return Err(DataError::UnexpectedEnd);
+        };
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], DataError> {
+        let Some((&taken, _)) = self.remaining().split_first_chunk::<N>() else {
+            return Err(DataError::UnexpectedEnd);
+        };
+        self.pos += N;
+        Ok(taken)
+    }
+
+    fn read_bool(&mut self) -> Result<bool, DataError> {
+        // Like `readBoolean`, any byte other than zero is true.
+        Ok(self.take_array::<1>()? != [0])
+    }
+
+    fn read_len(&mut self) -> Result<usize, DataError> {
+        let len = i32::from_be_bytes(self.take_array()?);
+        usize::try_from(len).map_err(|_| DataError::NegativeLength(len))
+    }
+
+    fn read_str(&mut self) -> Result<Cow<'de, str>, DataError> {
+        let len = u16::from_be_bytes(self.take_array()?);
+        let start = self.pos;
+        let payload = self.take(usize::from(len))?;
+        decode(payload).map_err(|error| DataError::Mutf8(error.offset(start)))
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+                visitor.$visit(<$ty>::from_be_bytes(self.take_array()?))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = DataError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, DataError> {
+        Err(DataError::NotSelfDescribing)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        visitor.visit_bool(self.read_bool()?)
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        let unit = u16::from_be_bytes(self.take_array()?);
+        match char::from_u32(u32::from(unit)) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(DataError::UnpairedSurrogate(unit)),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        match self.read_str()? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        let len = self.read_len()?;
+        visitor.visit_borrowed_bytes(self.take(len)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        if self.read_bool()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        let len = self.read_len()?;
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DataError> {
+        let len = self.read_len()?;
+        visitor.visit_map(Access { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, DataError> {
+        Err(DataError::NotSelfDescribing)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, DataError> {
+        Err(DataError::NotSelfDescribing)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Gives access to a known number of elements or entries.
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = DataError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DataError> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = DataError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DataError> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DataError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = DataError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), DataError> {
+        let index = u32::from_be_bytes(self.take_array()?);
+        let value = seed.deserialize(index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = DataError;
+
+    fn unit_variant(self) -> Result<(), DataError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, DataError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, DataError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DataError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+/// An error thrown when a value cannot be serialized to or deserialized from
+/// the format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DataError {
+    /// An error raised by a [`Serialize`] or [`Deserialize`] implementation.
+    Message(String),
+    /// The input ended before the end of the value.
+    UnexpectedEnd,
+    /// The input was not used up by the value.
+    TrailingBytes,
+    /// A string is invalid MUTF-8 data. The position given by
+    /// [`valid_up_to`](Error::valid_up_to) is relative to the start of the
+    /// input.
+    Mutf8(Error),
+    /// A string is too long to be framed by [`write_utf`].
+    TooLong(TooLong),
+    /// A character does not fit in a single UTF-16 code unit.
+    CharOutOfRange(char),
+    /// A character is a surrogate code unit, which a `char` cannot hold.
+    UnpairedSurrogate(u16),
+    /// A length is too large to be written as an `int`.
+    LengthOverflow(usize),
+    /// A length is negative.
+    NegativeLength(i32),
+    /// A sequence or map does not know its length ahead of time.
+    UnknownLength,
+    /// The type to deserialize is not known, which the format requires.
+    NotSelfDescribing,
+}
+
+impl fmt::Display for DataError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Message(message) => f.write_str(message),
+            DataError::UnexpectedEnd => f.write_str("unexpected end of input"),
+            DataError::TrailingBytes => f.write_str("trailing bytes after value"),
+            DataError::Mutf8(error) => error.fmt(f),
+            DataError::TooLong(error) => error.fmt(f),
+            DataError::CharOutOfRange(c) => {
+                write!(f, "character {c:?} does not fit in a UTF-16 code unit")
+            }
+            DataError::UnpairedSurrogate(unit) => {
+                write!(f, "unpaired surrogate {unit:#06X} is not a character")
+            }
+            DataError::LengthOverflow(len) => write!(f, "length {len} does not fit in an int"),
+            DataError::NegativeLength(len) => write!(f, "negative length {len}"),
+            DataError::UnknownLength => f.write_str("length of sequence or map is unknown"),
+            DataError::NotSelfDescribing => {
+                f.write_str("format is not self-describing, so the type must be known")
+            }
+        }
+    }
+}
+
+// `serde` requires its errors to implement `std::error::Error` if its `std`
+// feature is enabled, and `core::error::Error` or a stand-in otherwise, all of
+// which this trait refers to.
+impl ser::StdError for DataError {
+    #[cfg(feature = "std")]
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DataError::Mutf8(error) => Some(error),
+            DataError::TooLong(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl ser::Error for DataError {
+    #[inline]
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DataError::Message(msg.to_string())
+    }
+}
+
+impl de::Error for DataError {
+    #[inline]
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DataError::Message(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+    use alloc::{collections::BTreeMap, vec};
+
+    /// Serializes `value`, checks it against `bytes`, and reads it back.
+    fn assert_bytes<'de, T>(value: &T, bytes: &'de [u8])
+    where
+        T: Serialize + Deserialize<'de> + PartialEq + fmt::Debug,
+    {
+        assert_eq!(to_vec(value).unwrap(), bytes, "{value:?}");
+        assert_eq!(from_slice::<T>(bytes).unwrap(), *value);
+    }
+
+    /// A sequence that does not know its length ahead of time.
+    struct Unsized;
+
+    impl Serialize for Unsized {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq([1_u8, 2].iter().filter(|_| true))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Message<'a> {
+        Ping,
+        Move(i16, i16),
+        Chat { text: &'a str },
+        Id(u8),
+    }
+
+    #[test]
+    fn primitives() {
+        assert_bytes(&false, &[0x00]);
+        assert_bytes(&true, &[0x01]);
+        assert_bytes(&-2_i8, &[0xFE]);
+        assert_bytes(&0xAB_u8, &[0xAB]);
+        assert_bytes(&-2_i16, &[0xFF, 0xFE]);
+        assert_bytes(&0x1234_u16, &[0x12, 0x34]);
+        assert_bytes(&-2_i32, &[0xFF, 0xFF, 0xFF, 0xFE]);
+        assert_bytes(&0x1234_5678_u32, &[0x12, 0x34, 0x56, 0x78]);
+        assert_bytes(&-2_i64, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]);
+        assert_bytes(
+            &0x0102_0304_0506_0708_u64,
+            &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+        );
+        assert_bytes(&-2_i128, &[[0xFF; 15].as_slice(), &[0xFE]].concat());
+        assert_bytes(&1_u128, &[[0x00; 15].as_slice(), &[0x01]].concat());
+        // `writeFloat(1.5f)` and `writeDouble(-0.25)`.
+        assert_bytes(&1.5_f32, &[0x3F, 0xC0, 0x00, 0x00]);
+        assert_bytes(
+            &-0.25_f64,
+            &[0xBF, 0xD0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        );
+        assert_bytes(&(), &[]);
+        assert_bytes(&Some(7_u8), &[0x01, 0x07]);
+        assert_bytes(&None::<u8>, &[0x00]);
+
+        // Like `readBoolean`, any byte other than zero is true.
+        assert_eq!(from_slice::<bool>(&[0x02]), Ok(true));
+        assert_eq!(
+            from_slice::<u32>(&[0x00, 0x01]),
+            Err(DataError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn char_is_one_code_unit() {
+        assert_bytes(&'A', &[0x00, 0x41]);
+        assert_bytes(&'\0', &[0x00, 0x00]);
+        assert_bytes(&'\u{E9}', &[0x00, 0xE9]);
+        assert_bytes(&'\u{FFFF}', &[0xFF, 0xFF]);
+
+        assert_eq!(
+            to_vec(&'\u{1F600}'),
+            Err(DataError::CharOutOfRange('\u{1F600}'))
+        );
+        for unit in [0xD800_u16, 0xDBFF, 0xDC00, 0xDFFF] {
+            assert_eq!(
+                from_slice::<char>(&unit.to_be_bytes()),
+                Err(DataError::UnpairedSurrogate(unit)),
+            );
+        }
+    }
+
+    #[test]
+    fn strings() {
+        // `writeUTF("a\0\uD83D\uDE00")`.
+        let bytes = [
+            0x00, 0x09, b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80,
+        ];
+        assert_bytes(&String::from("a\0\u{1F600}"), &bytes);
+        assert_eq!(
+            from_slice::<Cow<'_, str>>(&bytes),
+            Ok(Cow::Owned("a\0\u{1F600}".into()))
+        );
+
+        // A string that is valid UTF-8 is borrowed from the input.
+        assert_bytes(&"hi", &[0x00, 0x02, b'h', b'i']);
+
+        let max = "\0".repeat(32_767);
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend_from_slice(&[0xC0, 0x80].repeat(32_767));
+        assert_bytes(&max, &bytes);
+
+        // One more null character takes up 65536 bytes in MUTF-8, although it
+        // is only 32768 bytes long in UTF-8.
+        let too_long = "\0".repeat(32_768);
+        assert_eq!(
+            to_vec(&too_long),
+            Err(DataError::TooLong(TooLong::new(65_536)))
+        );
+        assert_eq!(
+            to_vec(&"a".repeat(65_536)),
+            Err(DataError::TooLong(TooLong::new(65_536)))
+        );
+        assert_eq!(to_vec(&"a".repeat(65_535)).unwrap().len(), 65_537);
+    }
+
+    #[test]
+    fn lengths() {
+        assert_bytes(
+            &vec![1_u16, 2],
+            &[0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02],
+        );
+        assert_bytes(&Vec::<u8>::new(), &[0x00, 0x00, 0x00, 0x00]);
+        let map = BTreeMap::from([(1_u8, true), (2, false)]);
+        assert_bytes(&map, &[0x00, 0x00, 0x00, 0x02, 0x01, 0x01, 0x02, 0x00]);
+
+        // Tuples and structs have no length.
+        assert_bytes(&(1_u8, 2_u8), &[0x01, 0x02]);
+
+        for bytes in [&[0xFF, 0xFF, 0xFF, 0xFF][..], &[0x80, 0x00, 0x00, 0x00]] {
+            let expected = i32::from_be_bytes(bytes.try_into().unwrap());
+            assert_eq!(
+                from_slice::<Vec<u8>>(bytes),
+                Err(DataError::NegativeLength(expected))
+            );
+            assert_eq!(
+                from_slice::<BTreeMap<u8, u8>>(bytes),
+                Err(DataError::NegativeLength(expected))
+            );
+        }
+        assert_eq!(
+            from_slice::<Vec<u8>>(&[0x00, 0x00, 0x00, 0x02, 0x01]),
+            Err(DataError::UnexpectedEnd)
+        );
+        assert_eq!(to_vec(&Unsized), Err(DataError::UnknownLength));
+    }
+
+    #[test]
+    fn enum_variant_index() {
+        assert_bytes(&Message::Ping, &[0x00, 0x00, 0x00, 0x00]);
+        assert_bytes(
+            &Message::Move(-1, 2),
+            &[0x00, 0x00, 0x00, 0x01, 0xFF, 0xFF, 0x00, 0x02],
+        );
+        assert_bytes(
+            &Message::Chat { text: "hi" },
+            &[0x00, 0x00, 0x00, 0x02, 0x00, 0x02, b'h', b'i'],
+        );
+        assert_bytes(&Message::Id(9), &[0x00, 0x00, 0x00, 0x03, 0x09]);
+        assert!(matches!(
+            from_slice::<Message<'_>>(&[0x00, 0x00, 0x00, 0x04]),
+            Err(DataError::Message(_))
+        ));
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        assert_eq!(
+            from_slice::<u8>(&[0x01, 0x02]),
+            Err(DataError::TrailingBytes)
+        );
+        assert_eq!(
+            from_slice::<&str>(&[0x00, 0x01, b'a', 0x00]),
+            Err(DataError::TrailingBytes)
+        );
+        assert_eq!(from_slice::<()>(&[0x00]), Err(DataError::TrailingBytes));
+    }
+
+    #[test]
+    fn mutf8_error_offset() {
+        // The frame of the string starts at byte 3 and its payload at byte 5,
+        // so the invalid byte 2 bytes into the payload is at byte 7.
+        let bytes = [0x07, 0x00, 0x01, 0x00, 0x03, b'a', b'b', 0x80];
+        let Err(DataError::Mutf8(error)) = from_slice::<(u8, char, String)>(&bytes) else {
+            panic!()
+        };
+        assert_eq!(error.valid_up_to(), 7);
+        assert_eq!(error.error_len(), Some(1));
+
+        // A raw null byte is accepted, as it is by `decode`.
+        assert_eq!(from_slice::<&str>(&[0x00, 0x01, 0x00]), Ok("\0"));
+    }
+}
@@ -0,0 +1,481 @@
+//! Vectorized searches for the bytes that break a run.
+//!
+//! Every fast path in the crate skips over a run of bytes that are the same in
+//! MUTF-8 and UTF-8, so they all come down to finding the first byte of some
+//! [`ByteSet`]. This is done 32 bytes at a time with AVX2, detected at runtime
+//! if `std` is enabled, or 16 bytes at a time with SSE2 on `x86_64` and NEON on
+//! `aarch64`, both of which are always available there. Other targets read a
+//! `usize` at a time instead. Setting `--cfg mutf8_no_simd` forces the portable
+//! path everywhere, which is how it is tested.
+//!
+//! The decoders skip runs of bytes that are the same in UTF-8 with it and
+//! leave them to [`from_utf8`](core::str::from_utf8), while
+//! [`validate`](crate::validate) skips every run of non-null ASCII, not just
+//! the first, whenever its automaton is between sequences. The multi-byte
+//! sequences that a search stops at are still checked one byte at a time by
+//! `scan` and `dfa`.
+//!
+//! Whichever path is taken must give the same results as a plain byte-by-byte
+//! search, which the unit tests check for [`find`] and for the functions built
+//! on it.
+
+/// A set of bytes to search for, made up of every byte from `min` up, along
+/// with the null byte if `null` is set and the lead bytes of a null pair and
+/// of a surrogate if `lead_bytes` is set.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ByteSet {
+    null: bool,
+    lead_bytes: bool,
+    min: u8,
+}
+
+impl ByteSet {
+    /// The bytes that cannot be kept as they are when UTF-8 is encoded to
+    /// MUTF-8: the null byte and the lead bytes of 4-byte sequences.
+    pub(crate) const ENCODE: ByteSet = ByteSet {
+        null: true,
+        lead_bytes: false,
+        min: 0xF0,
+    };
+
+    /// The lead bytes of 4-byte sequences, along with the bytes that can never
+    /// appear in UTF-8 at all.
+    pub(crate) const FOUR_BYTE: ByteSet = ByteSet {
+        null: false,
+        lead_bytes: false,
+        min: 0xF0,
+    };
+
+    /// The bytes that end a run of non-null ASCII.
+    pub(crate) const NON_ASCII: ByteSet = ByteSet {
+        null: true,
+        lead_bytes: false,
+        min: 0x80,
+    };
+
+    /// The bytes that may start a sequence that is not the same in MUTF-8 and
+    /// UTF-8. Everything in between can be validated as UTF-8. If `strict` is
+    /// `true`, this includes the null byte.
+    pub(crate) const fn decode(strict: bool) -> ByteSet {
+        ByteSet {
+            null: strict,
+            lead_bytes: true,
+            min: 0xF0,
+        }
+    }
+
+    /// Returns `true` if `byte` is in the set.
+    #[inline]
+    pub(crate) fn contains(self, byte: u8) -> bool {
+        byte >= self.min
+            || (self.null && byte == 0x00)
+            || (self.lead_bytes && (byte == 0xC0 || byte == 0xED))
+    }
+}
+
+/// Returns the index of the first byte in `bytes` that is in `set`.
+#[inline]
+pub(crate) fn find(bytes: &[u8], set: ByteSet) -> Option<usize> {
+    if bytes.len() < 16 {
+        find_scalar(bytes, set)
+    } else {
+        imp::find(bytes, set)
+    }
+}
+
+#[inline]
+fn find_scalar(bytes: &[u8], set: ByteSet) -> Option<usize> {
+    bytes.iter().position(|&byte| set.contains(byte))
+}
+
+#[cfg(all(target_arch = "x86_64", not(mutf8_no_simd)))]
+mod imp {
+    use super::{find_scalar, ByteSet};
+    use core::arch::x86_64::{
+        __m128i, __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_max_epu8,
+        _mm256_movemask_epi8, _mm256_or_si256, _mm256_set1_epi8, _mm_cmpeq_epi8, _mm_loadu_si128,
+        _mm_max_epu8, _mm_movemask_epi8, _mm_or_si128, _mm_set1_epi8,
+    };
+
+    #[inline]
+    pub(super) fn find(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        if bytes.len() >= 64 && has_avx2() {
+            // SAFETY: AVX2 is available.
+            unsafe { find_avx2(bytes, set) }
+        } else {
+            // SAFETY: SSE2 is always available on `x86_64`.
+            unsafe { find_sse2(bytes, set) }
+        }
+    }
+
+    #[inline]
+    fn has_avx2() -> bool {
+        #[cfg(target_feature = "avx2")]
+        {
+            true
+        }
+        #[cfg(all(not(target_feature = "avx2"), feature = "std"))]
+        {
+            std::is_x86_feature_detected!("avx2")
+        }
+        #[cfg(all(not(target_feature = "avx2"), not(feature = "std")))]
+        {
+            false
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn find_avx2(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        let min = _mm256_set1_epi8(i8::from_ne_bytes([set.min]));
+        let null = _mm256_set1_epi8(0);
+        let null_pair = _mm256_set1_epi8(i8::from_ne_bytes([0xC0]));
+        let surrogate = _mm256_set1_epi8(i8::from_ne_bytes([0xED]));
+
+        let mut index = 0;
+        while index + 32 <= bytes.len() {
+            // SAFETY: The 32 bytes read are in bounds, and need not be aligned.
+            #[allow(clippy::cast_ptr_alignment)]
+            let chunk = unsafe { _mm256_loadu_si256(bytes.as_ptr().add(index).cast::<__m256i>()) };
+            let mut found = _mm256_cmpeq_epi8(_mm256_max_epu8(chunk, min), chunk);
+            if set.null {
+                found = _mm256_or_si256(found, _mm256_cmpeq_epi8(chunk, null));
+            }
+            if set.lead_bytes {
+                found = _mm256_or_si256(found, _mm256_cmpeq_epi8(chunk, null_pair));
+                found = _mm256_or_si256(found, _mm256_cmpeq_epi8(chunk, surrogate));
+            }
+            let mask = _mm256_movemask_epi8(found);
+            if mask != 0 {
+                return Some(index + mask.trailing_zeros() as usize);
+            }
+            index += 32;
+        }
+        // SAFETY: AVX2 implies SSE2.
+        unsafe { find_sse2(&bytes[index..], set) }.map(|found| index + found)
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn find_sse2(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        let min = _mm_set1_epi8(i8::from_ne_bytes([set.min]));
+        let null = _mm_set1_epi8(0);
+        let null_pair = _mm_set1_epi8(i8::from_ne_bytes([0xC0]));
+        let surrogate = _mm_set1_epi8(i8::from_ne_bytes([0xED]));
+
+        let mut index = 0;
+        while index + 16 <= bytes.len() {
+            // SAFETY: The 16 bytes read are in bounds, and need not be aligned.
+            #[allow(clippy::cast_ptr_alignment)]
+            let chunk = unsafe { _mm_loadu_si128(bytes.as_ptr().add(index).cast::<__m128i>()) };
+            let mut found = _mm_cmpeq_epi8(_mm_max_epu8(chunk, min), chunk);
+            if set.null {
+                found = _mm_or_si128(found, _mm_cmpeq_epi8(chunk, null));
+            }
+            if set.lead_bytes {
+                found = _mm_or_si128(found, _mm_cmpeq_epi8(chunk, null_pair));
+                found = _mm_or_si128(found, _mm_cmpeq_epi8(chunk, surrogate));
+            }
+            let mask = _mm_movemask_epi8(found);
+            if mask != 0 {
+                return Some(index + mask.trailing_zeros() as usize);
+            }
+            index += 16;
+        }
+        find_scalar(&bytes[index..], set).map(|found| index + found)
+    }
+}
+
+#[cfg(all(
+    target_arch = "aarch64",
+    target_feature = "neon",
+    target_endian = "little",
+    not(mutf8_no_simd),
+))]
+mod imp {
+    use super::{find_scalar, ByteSet};
+    use core::arch::aarch64::{
+        vceqq_u8, vceqzq_u8, vcgeq_u8, vdupq_n_u8, vget_lane_u64, vld1q_u8, vmaxvq_u8, vorrq_u8,
+        vreinterpret_u64_u8, vreinterpretq_u16_u8, vshrn_n_u16,
+    };
+
+    #[inline]
+    pub(super) fn find(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        // SAFETY: NEON is enabled.
+        unsafe { find_neon(bytes, set) }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn find_neon(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        let min = vdupq_n_u8(set.min);
+        let null_pair = vdupq_n_u8(0xC0);
+        let surrogate = vdupq_n_u8(0xED);
+
+        let mut index = 0;
+        while index + 16 <= bytes.len() {
+            // SAFETY: The 16 bytes read are in bounds.
+            let chunk = unsafe { vld1q_u8(bytes.as_ptr().add(index)) };
+            let mut found = vcgeq_u8(chunk, min);
+            if set.null {
+                found = vorrq_u8(found, vceqzq_u8(chunk));
+            }
+            if set.lead_bytes {
+                found = vorrq_u8(found, vceqq_u8(chunk, null_pair));
+                found = vorrq_u8(found, vceqq_u8(chunk, surrogate));
+            }
+            if vmaxvq_u8(found) != 0 {
+                // Narrowing each byte of the mask to 4 bits gives a `u64` with
+                // one nibble per byte, in order.
+                let nibbles = vreinterpret_u64_u8(vshrn_n_u16::<4>(vreinterpretq_u16_u8(found)));
+                let mask = vget_lane_u64::<0>(nibbles);
+                return Some(index + (mask.trailing_zeros() / 4) as usize);
+            }
+            index += 16;
+        }
+        find_scalar(&bytes[index..], set).map(|found| index + found)
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", not(mutf8_no_simd)),
+    all(
+        target_arch = "aarch64",
+        target_feature = "neon",
+        target_endian = "little",
+        not(mutf8_no_simd),
+    ),
+)))]
+mod imp {
+    use super::{find_scalar, ByteSet};
+    use core::mem::size_of;
+
+    const WORD: usize = size_of::<usize>();
+
+    #[inline]
+    const fn repeat(byte: u8) -> usize {
+        usize::from_ne_bytes([byte; WORD])
+    }
+
+    /// Returns a word with the high bit of some byte set if any byte of `word`
+    /// is zero. Bytes after a zero byte may be flagged as well.
+    #[inline]
+    const fn has_zero(word: usize) -> usize {
+        word.wrapping_sub(repeat(0x01)) & !word & repeat(0x80)
+    }
+
+    /// Returns a word with the high bit of exactly those bytes of `word` set
+    /// that are at least `min`, which must be at least `0x80`.
+    #[inline]
+    const fn at_least(word: usize, min: u8) -> usize {
+        let offset = repeat(0x80 - (min & 0x7F));
+        word & ((word & !repeat(0x80)) + offset) & repeat(0x80)
+    }
+
+    #[inline]
+    pub(super) fn find(bytes: &[u8], set: ByteSet) -> Option<usize> {
+        debug_assert!(set.min >= 0x80);
+        let mut chunks = bytes.chunks_exact(WORD);
+        for (index, chunk) in chunks.by_ref().enumerate() {
+            let mut word = [0; WORD];
+            word.copy_from_slice(chunk);
+            let word = usize::from_ne_bytes(word);
+
+            let mut found = at_least(word, set.min);
+            if set.null {
+                found |= has_zero(word);
+            }
+            if set.lead_bytes {
+                found |= has_zero(word ^ repeat(0xC0)) | has_zero(word ^ repeat(0xED));
+            }
+            // A flagged byte is not necessarily the first one, so the word is
+            // searched one byte at a time to find it.
+            if found != 0 {
+                if let Some(found) = find_scalar(chunk, set) {
+                    return Some(index * WORD + found);
+                }
+            }
+        }
+        let tail = bytes.len() - chunks.remainder().len();
+        find_scalar(chunks.remainder(), set).map(|found| tail + found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, find_scalar, ByteSet};
+    use crate::{decode, decode_strict, dfa, encode, is_valid, is_valid_mutf8, validate, MStr};
+    use alloc::{borrow::Cow, string::String, vec::Vec};
+
+    const SETS: [ByteSet; 5] = [
+        ByteSet::ENCODE,
+        ByteSet::FOUR_BYTE,
+        ByteSet::NON_ASCII,
+        ByteSet::decode(false),
+        ByteSet::decode(true),
+    ];
+
+    /// Fills `buffer` with bytes that are not in `set`, cycling through all of
+    /// them so that bytes close to every boundary show up.
+    fn background(buffer: &mut [u8], set: ByteSet) {
+        let others: Vec<u8> = (0..=255).filter(|&byte| !set.contains(byte)).collect();
+        for (index, byte) in buffer.iter_mut().enumerate() {
+            *byte = others[index * 7 % others.len()];
+        }
+    }
+
+    #[test]
+    fn find_matches_scalar_at_every_alignment_and_length() {
+        let mut buffer = [0; 192];
+        for set in SETS {
+            let members: Vec<u8> = (0..=255).filter(|&byte| set.contains(byte)).collect();
+            for offset in 0..32 {
+                for len in 0..=buffer.len() - 32 {
+                    background(&mut buffer, set);
+                    let bytes = &mut buffer[offset..offset + len];
+                    assert_eq!(find(bytes, set), None, "{set:?} {offset} {len}");
+                    for at in 0..len {
+                        let other = bytes[at];
+                        bytes[at] = members[(at + len) % members.len()];
+                        assert_eq!(find(bytes, set), find_scalar(bytes, set));
+                        assert_eq!(find(bytes, set), Some(at), "{set:?} {offset} {len}");
+                        bytes[at] = other;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_matches_scalar_for_every_byte() {
+        let mut buffer = [0; 160];
+        for set in SETS {
+            for byte in 0..=255 {
+                for at in [0, 15, 16, 31, 32, 63, 64, 95, 127, 159] {
+                    background(&mut buffer, set);
+                    buffer[at] = byte;
+                    assert_eq!(
+                        find(&buffer, set),
+                        find_scalar(&buffer, set),
+                        "{set:?} {byte:#04X} {at}",
+                    );
+                    assert_eq!(find(&buffer, set).is_some(), set.contains(byte));
+                }
+            }
+        }
+    }
+
+    /// Encodes `c` to MUTF-8 one UTF-16 code unit at a time.
+    #[allow(clippy::cast_possible_truncation)]
+    fn encode_units(c: char, out: &mut Vec<u8>) {
+        if c == '\0' {
+            return out.extend_from_slice(&[0xC0, 0x80]);
+        }
+        for unit in c.encode_utf16(&mut [0; 2]) {
+            let unit = u32::from(*unit);
+            match unit {
+                0x00..=0x7F => out.push(unit as u8),
+                0x80..=0x7FF => {
+                    out.extend_from_slice(&[0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8]);
+                }
+                _ => out.extend_from_slice(&[
+                    0xE0 | (unit >> 12) as u8,
+                    0x80 | (unit >> 6 & 0x3F) as u8,
+                    0x80 | (unit & 0x3F) as u8,
+                ]),
+            }
+        }
+    }
+
+    /// Checks `validate` and `is_valid_mutf8` against the automaton run over
+    /// every byte, and `is_valid` against a byte-by-byte search.
+    fn assert_matches_scalar(s: &str, bytes: &[u8]) {
+        let scalar = !s.bytes().any(|byte| ByteSet::ENCODE.contains(byte));
+        assert_eq!(is_valid(s), scalar, "{s:?}");
+        let scalar = dfa::run(bytes);
+        assert_eq!(is_valid_mutf8(bytes), scalar.is_ok(), "{bytes:02X?}");
+        assert_eq!(
+            validate(bytes).map_err(|error| error.valid_up_to()),
+            scalar,
+            "{bytes:02X?}",
+        );
+    }
+
+    #[test]
+    fn functions_built_on_find_match_scalar() {
+        for filler in ['a', '\u{E9}', '\u{6F22}'] {
+            for special in ['\0', '\u{1F600}'] {
+                for len in 0..80 {
+                    for at in 0..=len {
+                        let mut s = String::from(filler).repeat(len);
+                        assert!(is_valid(&s));
+                        s.insert(
+                            s.char_indices().nth(at).map_or(s.len(), |(i, _)| i),
+                            special,
+                        );
+
+                        let mut expected = Vec::new();
+                        s.chars().for_each(|c| encode_units(c, &mut expected));
+                        assert!(!is_valid(&s));
+                        assert_eq!(crate::len(&s), expected.len());
+                        assert_eq!(encode(&s), expected);
+                        assert_eq!(decode(&expected), Ok(Cow::Borrowed(&*s)));
+                        assert_eq!(decode_strict(&expected), Ok(Cow::Borrowed(&*s)));
+                        assert!(MStr::from_bytes(&expected).is_ok());
+                        assert!(is_valid_mutf8(&expected));
+                        assert_matches_scalar(&s, &expected);
+
+                        // A stray continuation byte right after the special
+                        // character is the first invalid sequence.
+                        let mut valid_up_to = Vec::new();
+                        s.chars()
+                            .take(at + 1)
+                            .for_each(|c| encode_units(c, &mut valid_up_to));
+                        let valid_up_to = valid_up_to.len();
+                        let mut invalid = expected.clone();
+                        invalid.insert(valid_up_to, 0x80);
+                        for error in [
+                            decode(&invalid).unwrap_err(),
+                            decode_strict(&invalid).unwrap_err(),
+                            MStr::from_bytes(&invalid).unwrap_err(),
+                            validate(&invalid).unwrap_err(),
+                        ] {
+                            assert_eq!(
+                                (error.valid_up_to(), error.error_len()),
+                                (valid_up_to, Some(1)),
+                            );
+                        }
+                        assert_matches_scalar(&s, &invalid);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn validate_matches_scalar_around_ascii_runs() {
+        // Every ASCII run, long or short, between two multi-byte sequences is
+        // skipped, so the byte that ends it must be found wherever it is.
+        let ends: [&[u8]; 6] = [
+            &[0x00],
+            &[0x80],
+            &[0xC0, 0x80],
+            &[0xC3, 0xA9],
+            &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+            &[0xF0, 0x9F, 0x98, 0x80],
+        ];
+        for lead in [&[][..], &[0xC3, 0xA9], &[0xE6, 0xBC, 0xA2]] {
+            for run in 0..80 {
+                for end in ends {
+                    let mut bytes = lead.to_vec();
+                    bytes.extend_from_slice(&b"a".repeat(run));
+                    bytes.extend_from_slice(end);
+                    bytes.extend_from_slice(&b"b".repeat(run));
+                    bytes.extend_from_slice(lead);
+                    let s = String::from_utf8_lossy(&bytes);
+                    assert_matches_scalar(&s, &bytes);
+                    for cut in 0..bytes.len() {
+                        assert_matches_scalar(&s, &bytes[..cut]);
+                    }
+                }
+            }
+        }
+    }
+}
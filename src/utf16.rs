@@ -0,0 +1,343 @@
+use crate::{
+    error::{Error, ErrorKind, Invalid},
+    scan, Supplementary, NULL_PAIR,
+};
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+/// Decodes a slice of MUTF-8 bytes to UTF-16 code units.
+///
+/// MUTF-8 is a byte serialization of UTF-16, so every 1, 2 or 3-byte sequence
+/// becomes exactly one code unit. Unlike [`decode`](crate::decode), a surrogate
+/// that is not part of a surrogate pair is not an error; it is returned as-is,
+/// the same way a `java.lang.String` would hold it. This makes it possible to
+/// decode any Java string without loss.
+///
+/// As with `decode`, a supplementary character may also be written as a 4-byte
+/// sequence, which becomes a surrogate pair, unless surrogate pairs were
+/// written as two 3-byte sequences before it. Once a 4-byte sequence is found,
+/// a surrogate pair written as two 3-byte sequences is an error instead.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the input contains a byte sequence that does not
+/// encode a code unit.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let mutf8_data = &[b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81];
+/// // A null pair becomes a single code unit, and a surrogate pair becomes two.
+/// assert_eq!(
+///     mutf8::decode_utf16(mutf8_data),
+///     Ok(vec![0x0061, 0x0000, 0xD801, 0xDC01]),
+/// );
+///
+/// // A lone surrogate cannot be decoded to a string slice, but it can be
+/// // decoded to UTF-16.
+/// let mutf8_data = &[0xED, 0xA0, 0x81];
+/// assert!(mutf8::decode(mutf8_data).is_err());
+/// assert_eq!(mutf8::decode_utf16(mutf8_data), Ok(vec![0xD801]));
+/// ```
+#[inline]
+pub fn decode_utf16(bytes: &[u8]) -> Result<Vec<u16>, Error> {
+    let mut decoded = Vec::with_capacity(units_len(bytes));
+    for unit in decode_utf16_iter(bytes) {
+        decoded.push(unit?);
+    }
+    Ok(decoded)
+}
+
+/// Returns how many UTF-16 code units a slice of MUTF-8 bytes decodes to.
+///
+/// Every code unit starts with exactly one byte that is not a continuation
+/// byte, apart from the low surrogate of a 4-byte sequence, so this is exact
+/// for valid input.
+#[inline]
+fn units_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .map(|&byte| usize::from(byte & 0xC0 != 0x80) + usize::from(byte >= 0xF0))
+        .sum()
+}
+
+/// Returns an iterator over the UTF-16 code units encoded by a slice of MUTF-8
+/// bytes.
+///
+/// This is the lazy form of [`decode_utf16`]. When an invalid sequence is
+/// found, the iterator yields an [`Error`] for it and then carries on after
+/// it, unless the input ended in the middle of a sequence.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mutf8::ErrorKind;
+///
+/// let mut iter = mutf8::decode_utf16_iter(&[0xED, 0xB0, 0x81, 0x80, b'a', 0xE2]);
+/// assert_eq!(iter.next(), Some(Ok(0xDC01)));
+/// assert_eq!(iter.next().unwrap().unwrap_err().kind(), ErrorKind::InvalidByte);
+/// assert_eq!(iter.next(), Some(Ok(0x0061)));
+/// assert_eq!(iter.next().unwrap().unwrap_err().kind(), ErrorKind::UnexpectedEnd);
+/// assert_eq!(iter.next(), None);
+/// ```
+#[must_use]
+#[inline]
+pub fn decode_utf16_iter(bytes: &[u8]) -> DecodeUtf16<'_> {
+    DecodeUtf16 {
+        bytes,
+        index: 0,
+        low: None,
+        form: Supplementary::default(),
+    }
+}
+
+/// An iterator that decodes MUTF-8 bytes to UTF-16 code units.
+///
+/// This struct is created by [`decode_utf16_iter`]. See its documentation for
+/// more.
+#[derive(Clone, Debug)]
+pub struct DecodeUtf16<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    /// The low surrogate of a 4-byte sequence whose high surrogate was
+    /// already returned.
+    low: Option<u16>,
+    form: Supplementary,
+}
+
+impl Iterator for DecodeUtf16<'_> {
+    type Item = Result<u16, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(low) = self.low.take() {
+            return Some(Ok(low));
+        }
+        let rest = self
+            .bytes
+            .get(self.index..)
+            .filter(|rest| !rest.is_empty())?;
+        if let [0xED, 0xA0..=0xAF, 0x80..=0xBF, 0xED, 0xB0..=0xBF, 0x80..=0xBF, ..] = *rest {
+            if self.form.four_byte {
+                let invalid = Invalid::new(ErrorKind::SplitSurrogatePair, 6);
+                let error = Error::new(self.index, invalid);
+                self.index += 6;
+                return Some(Err(error));
+            }
+            self.form.surrogate_pair = true;
+        }
+        match scan::next_code_unit(rest, false) {
+            Ok((unit, width)) => {
+                self.index += width;
+                Some(Ok(unit))
+            }
+            Err(Invalid {
+                kind: ErrorKind::FourByteSequence,
+                len: Some(4),
+            }) if !self.form.surrogate_pair => {
+                self.form.four_byte = true;
+                self.index += 4;
+                // SAFETY: A 4-byte sequence always encodes a supplementary
+                // character.
+                let c = unsafe { char::from_u32_unchecked(scan::four_byte_code_point(rest)) };
+                let mut units = [0; 2];
+                c.encode_utf16(&mut units);
+                self.low = Some(units[1]);
+                Some(Ok(units[0]))
+            }
+            Err(invalid) => {
+                let error = Error::new(self.index, invalid);
+                self.index += invalid.len.map_or(rest.len(), usize::from);
+                Some(Err(error))
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.bytes.len().saturating_sub(self.index);
+        let low = usize::from(self.low.is_some());
+        // An error may use up a whole surrogate pair, or the rest of the input
+        // if it is cut short, and still yield a single item.
+        (remaining.div_ceil(6) + low, Some(remaining + low))
+    }
+}
+
+impl FusedIterator for DecodeUtf16<'_> {}
+
+/// Encodes a slice of UTF-16 code units to MUTF-8 bytes.
+///
+/// Every code unit is written as a 1, 2 or 3-byte sequence, the same way
+/// `java.io.DataOutputStream.writeUTF` writes them. A surrogate pair therefore
+/// becomes two 3-byte sequences, a surrogate that is not part of a pair is
+/// written on its own, and U+0000 is written as `0xC0 0x80`.
+///
+/// Together with [`decode_utf16`], this converts any Java string to and from
+/// MUTF-8 without loss.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// let utf16_data = &[0x0061, 0x0000, 0xD801, 0xDC01];
+/// assert_eq!(
+///     mutf8::encode_utf16(utf16_data),
+///     vec![b'a', 0xC0, 0x80, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81],
+/// );
+///
+/// // A lone surrogate is encoded as-is and survives a round trip.
+/// let utf16_data = &[0xDC01, 0x0062];
+/// let mutf8_data = mutf8::encode_utf16(utf16_data);
+/// assert_eq!(mutf8_data, vec![0xED, 0xB0, 0x81, b'b']);
+/// assert_eq!(mutf8::decode_utf16(&mutf8_data), Ok(utf16_data.to_vec()));
+/// ```
+#[must_use]
+pub fn encode_utf16(units: &[u16]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(len_utf16(units));
+
+    for &unit in units {
+        let (bytes, width) = encode_code_unit(unit);
+        encoded.extend_from_slice(&bytes[..width]);
+    }
+
+    encoded
+}
+
+/// Encodes a single UTF-16 code unit, returning the bytes along with how many
+/// of them are used.
+#[inline]
+pub(crate) fn encode_code_unit(unit: u16) -> ([u8; 3], usize) {
+    match unit {
+        0x0000 => ([NULL_PAIR[0], NULL_PAIR[1], 0], 2),
+        0x0001..=0x007F => ([(unit & 0x7F) as u8, 0, 0], 1),
+        0x0080..=0x07FF => (
+            [
+                0b1100_0000 | (unit >> 6 & 0b1_1111) as u8,
+                0b1000_0000 | (unit & 0b11_1111) as u8,
+                0,
+            ],
+            2,
+        ),
+        _ => (
+            [
+                0b1110_0000 | (unit >> 12) as u8,
+                0b1000_0000 | (unit >> 6 & 0b11_1111) as u8,
+                0b1000_0000 | (unit & 0b11_1111) as u8,
+            ],
+            3,
+        ),
+    }
+}
+
+/// Encodes a supplementary code point as the two 3-byte sequences of its
+/// surrogate pair.
+#[inline]
+pub(crate) fn encode_supplementary(code_point: u32) -> [u8; 6] {
+    let code_point = code_point - 0x10000;
+    let (high, _) = encode_code_unit(0xD800 | (code_point >> 10 & 0x3FF) as u16);
+    let (low, _) = encode_code_unit(0xDC00 | (code_point & 0x3FF) as u16);
+    [high[0], high[1], high[2], low[0], low[1], low[2]]
+}
+
+/// Returns how many bytes in MUTF-8 are required to encode a single UTF-16
+/// code unit.
+#[inline]
+pub(crate) fn code_unit_len(unit: u16) -> usize {
+    match unit {
+        0x0001..=0x007F => 1,
+        0x0000 | 0x0080..=0x07FF => 2,
+        _ => 3,
+    }
+}
+
+/// Given a slice of UTF-16 code units, this function returns how many bytes in
+/// MUTF-8 are required to encode them.
+///
+/// This is the counterpart to [`len`](crate::len) for [`encode_utf16`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// assert_eq!(mutf8::len_utf16(&[0x0061]), 1);
+/// assert_eq!(mutf8::len_utf16(&[0x0000]), 2);
+/// assert_eq!(mutf8::len_utf16(&[0xD801, 0xDC01]), 6);
+///
+/// // The result is the same as that of 'len' for the same string.
+/// let utf16_data: Vec<u16> = "\0\u{10401}".encode_utf16().collect();
+/// assert_eq!(mutf8::len_utf16(&utf16_data), mutf8::len("\0\u{10401}"));
+/// ```
+#[must_use]
+pub fn len_utf16(units: &[u16]) -> usize {
+    units.iter().map(|&unit| code_unit_len(unit)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_utf16_reserves_units() {
+        let mutf8_data = [
+            b'a', 0xC0, 0x80, 0xE3, 0x81, 0x82, 0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81,
+        ];
+        let decoded = decode_utf16(&mutf8_data).unwrap();
+        assert_eq!(decoded, [0x0061, 0x0000, 0x3042, 0xD801, 0xDC01]);
+        assert_eq!(units_len(&mutf8_data), decoded.len());
+    }
+
+    #[test]
+    fn decode_utf16_four_byte_sequences() {
+        let inputs: [&[u8]; 4] = [
+            &[b'a', 0xF0, 0x9F, 0x98, 0x80, 0xC0, 0x80],
+            &[0xF0, 0x9F, 0x98, 0x80, 0xF0, 0x90, 0x90, 0x81],
+            &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+            &[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xF0, 0x9F, 0x98, 0x80],
+        ];
+        for input in inputs {
+            let expected = crate::decode(input).map(|s| s.encode_utf16().collect::<Vec<_>>());
+            let decoded = decode_utf16(input);
+            assert_eq!(decoded, expected, "{input:X?}");
+            if let Ok(decoded) = decoded {
+                assert_eq!(units_len(input), decoded.len());
+            }
+        }
+
+        // Lone surrogates do not decide which form is used.
+        let input = [0xED, 0xA0, 0xBD, 0xF0, 0x9F, 0x98, 0x80, 0xED, 0xB8, 0x80];
+        assert_eq!(
+            decode_utf16(&input),
+            Ok(alloc::vec![0xD83D, 0xD83D, 0xDE00, 0xDE00]),
+        );
+    }
+
+    #[test]
+    fn decode_utf16_iter_size_hint() {
+        let inputs: [&[u8]; 4] = [
+            &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80],
+            &[0xF0, 0x9F, 0x98, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8],
+            &[b'a', 0xED, 0xA0, 0xBD, 0xED, 0xB8],
+            &[
+                0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80, 0xC0, 0x80, 0xE3, 0x81, 0x82,
+            ],
+        ];
+        for input in inputs {
+            let mut iter = decode_utf16_iter(input);
+            loop {
+                let (lower, upper) = iter.size_hint();
+                let count = iter.clone().count();
+                assert!(lower <= count, "{input:X?} {lower} {count}");
+                assert!(upper.is_some_and(|upper| count <= upper), "{input:X?}");
+                if iter.next().is_none() {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    error::{Error, ErrorKind, Invalid},
+    scan::{self, is_continuation_byte},
+    utf16::{encode_code_unit, encode_supplementary},
+    Supplementary, NULL_PAIR,
+};
+use alloc::{borrow::Cow, str::from_utf8, vec::Vec};
+
+/// Converts a slice of MUTF-8 bytes to WTF-8 bytes.
+///
+/// [WTF-8] is a superset of UTF-8 that can also hold surrogates that are not
+/// part of a surrogate pair, which makes it a compact way to store potentially
+/// ill-formed UTF-16, such as a Java string, on the Rust side. Every surrogate
+/// pair in the input is merged into a 4-byte sequence, every lone surrogate is
+/// kept as its 3-byte sequence, and `0xC0 0x80` becomes a null byte.
+///
+/// If the slice of bytes is already valid UTF-8, it is also valid WTF-8, and
+/// `decode_wtf8()` returns it without allocating additional memory. Input is
+/// otherwise validated by the same rules as [`decode`](crate::decode), except
+/// that lone surrogates are accepted. In particular, raw null bytes are kept,
+/// and so are 4-byte UTF-8 sequences, as long as supplementary characters are
+/// not also written as surrogate pairs.
+///
+/// [WTF-8]: https://simonsapin.github.io/wtf-8/
+///
+/// # Errors
+///
+/// Returns [`Error`] if the input is invalid MUTF-8 data.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+///
+/// let str = "Hello, world!";
+/// assert_eq!(mutf8::decode_wtf8(str.as_bytes()), Ok(Cow::Borrowed(str.as_bytes())));
+///
+/// // A surrogate pair becomes a 4-byte sequence, while a lone surrogate is
+/// // kept as-is.
+/// let mutf8_data = &[0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, 0xC0, 0x80, 0xED, 0xA0, 0x81];
+/// assert_eq!(
+///     mutf8::decode_wtf8(mutf8_data),
+///     Ok(Cow::<[u8]>::Owned(vec![0xF0, 0x90, 0x90, 0x81, 0x00, 0xED, 0xA0, 0x81])),
+/// );
+/// ```
+#[inline]
+pub fn decode_wtf8(bytes: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    if from_utf8(bytes).is_ok() {
+        Ok(Cow::Borrowed(bytes))
+    } else {
+        decode_mutf8_to_wtf8(bytes).map(Cow::Owned)
+    }
+}
+
+#[inline(never)]
+#[cold]
+fn decode_mutf8_to_wtf8(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut form = Supplementary::default();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let (unit, width) = match scan::next_code_unit(&bytes[index..], false) {
+            Ok(unit) => unit,
+            Err(Invalid {
+                kind: ErrorKind::FourByteSequence,
+                len: Some(4),
+            }) if !form.surrogate_pair => {
+                form.four_byte = true;
+                decoded.extend_from_slice(&bytes[index..index + 4]);
+                index += 4;
+                continue;
+            }
+            Err(invalid) => return Err(Error::new(index, invalid)),
+        };
+
+        if (0xD800..=0xDBFF).contains(&unit) && index + width < bytes.len() {
+            if let Ok((low @ 0xDC00..=0xDFFF, low_width)) =
+                scan::next_code_unit(&bytes[index + width..], false)
+            {
+                if form.four_byte {
+                    let invalid = Invalid::new(ErrorKind::SplitSurrogatePair, 6);
+                    return Err(Error::new(index, invalid));
+                }
+                form.surrogate_pair = true;
+                // SAFETY: A surrogate pair always combines into a valid `char`.
+                let c = unsafe { char::from_u32_unchecked(scan::combine_surrogates(unit, low)) };
+                decoded.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                index += width + low_width;
+                continue;
+            }
+        }
+        index += width;
+
+        if unit == 0 {
+            decoded.push(0);
+        } else {
+            let (encoded, width) = encode_code_unit(unit);
+            decoded.extend_from_slice(&encoded[..width]);
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Converts a slice of WTF-8 bytes to MUTF-8 bytes.
+///
+/// This is the inverse of [`decode_wtf8`]: every 4-byte sequence is split into
+/// the two 3-byte sequences of its surrogate pair, every lone surrogate is kept
+/// as-is, and a null byte becomes `0xC0 0x80`.
+///
+/// If the WTF-8 representation is identical to the MUTF-8 one, this function
+/// returns the input without allocating additional memory.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the input is invalid WTF-8 data. Besides the errors
+/// UTF-8 itself can have, this includes a surrogate pair written as two
+/// 3-byte sequences, which is reported as [`ErrorKind::SplitSurrogatePair`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # extern crate alloc;
+/// use alloc::borrow::Cow;
+///
+/// let wtf8_data = &[0xF0, 0x90, 0x90, 0x81, 0x00, 0xED, 0xA0, 0x81];
+/// let mutf8_data = mutf8::encode_wtf8(wtf8_data).unwrap();
+/// assert_eq!(
+///     mutf8_data,
+///     Cow::<[u8]>::Owned(vec![0xED, 0xA0, 0x81, 0xED, 0xB0, 0x81, 0xC0, 0x80, 0xED, 0xA0, 0x81]),
+/// );
+/// assert_eq!(mutf8::decode_wtf8(&mutf8_data), Ok(Cow::Borrowed(&wtf8_data[..])));
+/// ```
+#[inline]
+pub fn encode_wtf8(bytes: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    let mut encoded = Vec::new();
+    let mut index = 0;
+    let mut copied = 0;
+
+    while index < bytes.len() {
+        let (code_point, width) =
+            next_wtf8_code_point(&bytes[index..]).map_err(|e| Error::new(index, e))?;
+        match code_point {
+            0x0000 | 0x10000.. => {
+                if encoded.is_empty() {
+                    encoded.reserve(bytes.len() + 2);
+                }
+                encoded.extend_from_slice(&bytes[copied..index]);
+                if code_point == 0 {
+                    encoded.extend_from_slice(&NULL_PAIR);
+                } else {
+                    encoded.extend_from_slice(&encode_supplementary(code_point));
+                }
+                copied = index + width;
+            }
+            _ => {}
+        }
+        index += width;
+    }
+
+    if encoded.is_empty() {
+        Ok(Cow::Borrowed(bytes))
+    } else {
+        encoded.extend_from_slice(&bytes[copied..]);
+        Ok(Cow::Owned(encoded))
+    }
+}
+
+/// Decodes the WTF-8 code point at the start of `bytes`, returning it along with
+/// the number of bytes it occupies. Surrogate code points are returned as-is.
+///
+/// `bytes` must not be empty.
+fn next_wtf8_code_point(bytes: &[u8]) -> Result<(u32, usize), Invalid> {
+    let first = bytes[0];
+    let (width, min_second, max_second) = match first {
+        0x00..=0x7F => return Ok((u32::from(first), 1)),
+        0xC0..=0xC1 => return Err(Invalid::new(ErrorKind::Overlong, 1)),
+        0xC2..=0xDF => (2_u8, 0x80, 0xBF),
+        0xE0 => (3, 0xA0, 0xBF),
+        0xE1..=0xEF => (3, 0x80, 0xBF),
+        0xF0 => (4, 0x90, 0xBF),
+        0xF1..=0xF3 => (4, 0x80, 0xBF),
+        0xF4 => (4, 0x80, 0x8F),
+        _ => return Err(Invalid::new(ErrorKind::InvalidByte, 1)),
+    };
+
+    let mut code_point = u32::from(first) & (0x7F >> width);
+    for index in 1..width {
+        let Some(&byte) = bytes.get(usize::from(index)) else {
+            return Err(Invalid::INCOMPLETE);
+        };
+        if index == 1 && is_continuation_byte(byte) && !(min_second..=max_second).contains(&byte) {
+            let kind = if byte < min_second {
+                ErrorKind::Overlong
+            } else {
+                ErrorKind::InvalidContinuation
+            };
+            return Err(Invalid::new(kind, 1));
+        }
+        if !is_continuation_byte(byte) {
+            return Err(Invalid::new(ErrorKind::InvalidContinuation, index));
+        }
+        code_point = code_point << 6 | u32::from(byte & 0x3F);
+    }
+
+    if (0xD800..=0xDBFF).contains(&code_point)
+        && bytes.get(3) == Some(&0xED)
+        && bytes
+            .get(4)
+            .is_some_and(|byte| (0xB0..=0xBF).contains(byte))
+        && bytes.get(5).copied().is_some_and(is_continuation_byte)
+    {
+        return Err(Invalid::new(ErrorKind::SplitSurrogatePair, 6));
+    }
+
+    Ok((code_point, usize::from(width)))
+}
@@ -0,0 +1,15 @@
+// Compiled into the class file that the tests of the `classfile` module parse.
+//
+// Recompile it from this directory with:
+//
+//     javac --release 8 Constants.java
+
+public class Constants {
+    static final long LONG = 0x0123_4567_89AB_CDEFL;
+    static final double DOUBLE = 1.5;
+    static final String STRING = "\0\uD83D\uDE00";
+
+    long sum(long a) {
+        return a + LONG + (long) DOUBLE + STRING.length();
+    }
+}
@@ -0,0 +1,105 @@
+// Writes the streams that the tests of the `java_serialization` module parse.
+//
+// Regenerate them from this directory with:
+//
+//     javac -d /tmp Gen.java && java -cp /tmp Gen
+
+import java.io.*;
+import java.lang.reflect.*;
+import java.util.*;
+
+public class Gen {
+    static class Base implements Serializable {
+        private static final long serialVersionUID = 1L;
+        int id = 7;
+        String name = "b\0";
+    }
+    static class Point extends Base {
+        private static final long serialVersionUID = 2L;
+        long x = -1;
+        double y = 0.5;
+        char c = '\u00e9';
+        boolean flag = true;
+        byte by = -2;
+        short sh = 300;
+        float f = 1.5f;
+        int[] values = {1, 2};
+        String label = "\uD83D\uDE00";
+    }
+    static class Annotated implements Serializable {
+        private static final long serialVersionUID = 3L;
+        int n = 1;
+        private void writeObject(ObjectOutputStream out) throws IOException {
+            out.defaultWriteObject();
+            out.writeInt(42);
+            out.writeObject("extra");
+        }
+    }
+    static class Handler implements InvocationHandler, Serializable {
+        private static final long serialVersionUID = 4L;
+        public Object invoke(Object proxy, Method method, Object[] args) { return null; }
+    }
+    static class Holder implements Serializable {
+        private static final long serialVersionUID = 5L;
+        Object inner = new Object();
+    }
+    static class Node implements Serializable {
+        private static final long serialVersionUID = 6L;
+        Node next;
+    }
+    enum Color { RED, GREEN }
+
+    interface Body { void write(ObjectOutputStream out) throws IOException; }
+
+    static void gen(String name, Body body) throws IOException {
+        ByteArrayOutputStream bytes = new ByteArrayOutputStream();
+        ObjectOutputStream out = new ObjectOutputStream(bytes);
+        try {
+            body.write(out);
+        } catch (NotSerializableException e) {
+            // TC_EXCEPTION has been written.
+        }
+        out.flush();
+        try (FileOutputStream file = new FileOutputStream(name + ".ser")) {
+            file.write(bytes.toByteArray());
+        }
+    }
+
+    public static void main(String[] args) throws Exception {
+        gen("points", out -> {
+            out.writeObject(new Point());
+            out.writeObject(new Point());
+        });
+        gen("annotated", out -> out.writeObject(new Annotated()));
+        gen("proxy", out -> out.writeObject(Proxy.newProxyInstance(
+                Gen.class.getClassLoader(), new Class<?>[] {Runnable.class}, new Handler())));
+        gen("reset", out -> {
+            out.writeObject("x");
+            out.reset();
+            out.writeObject("x");
+            out.writeObject("y");
+        });
+        gen("exception", out -> {
+            out.writeObject("before");
+            out.writeObject(new Holder());
+        });
+        gen("misc", out -> {
+            out.writeObject(Color.GREEN);
+            out.writeObject(new String[] {"a", null, "a"});
+            out.writeObject(String.class);
+            out.writeInt(0x01020304);
+        });
+        for (int depth : new int[] {200, 300}) {
+            final int d = depth;
+            gen("nodes" + depth, out -> {
+                Node head = null;
+                for (int i = 0; i < d; i++) {
+                    Node node = new Node();
+                    node.next = head;
+                    head = node;
+                }
+                out.writeObject(head);
+            });
+        }
+    }
+}